@@ -0,0 +1,125 @@
+use crate::error::AppError;
+use fs2::FileExt;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// Serializes writers inside this process; the lock file covers other processes.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+// One lock file in `lock_dir` (the app data dir) serves every target, so no
+// lock files are left next to the user's files
+const LOCK_FILE: &str = ".write.lock";
+
+// Replaces `path` with `contents` so that readers and crashes only ever see
+// the old file or the complete new one, never a truncated mix.
+pub fn write(lock_dir: &Path, path: &Path, contents: &[u8]) -> Result<(), AppError> {
+    let _guard = WRITE_LOCK.lock()
+        .map_err(|e| AppError::internal(format!("Failed to lock writer: {}", e)))?;
+
+    let lock_file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_dir.join(LOCK_FILE))
+        .map_err(|e| AppError::io("Failed to open lock file", e))?;
+    lock_file.lock_exclusive()
+        .map_err(|e| AppError::io("Failed to lock file", e))?;
+
+    let result = replace(path, contents);
+    let _ = lock_file.unlock();
+    result
+}
+
+fn replace(path: &Path, contents: &[u8]) -> Result<(), AppError> {
+    let temp_path = sibling(path, &format!("tmp-{}", std::process::id()))?;
+
+    let written = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(AppError::io("Failed to write file", e));
+    }
+
+    // Persist the rename itself; not supported for directories on Windows
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        File::open(parent)
+            .and_then(|dir| dir.sync_all())
+            .map_err(|e| AppError::io("Failed to sync directory", e))?;
+    }
+    Ok(())
+}
+
+// `.name.suffix` next to `path`, so the temp file is on the same filesystem
+// and the rename stays atomic.
+fn sibling(path: &Path, suffix: &str) -> Result<PathBuf, AppError> {
+    let name = path.file_name()
+        .ok_or_else(|| AppError::invalid_input(format!("Invalid file path: {}", path.display())))?;
+    Ok(path.with_file_name(format!(".{}.{}", name.to_string_lossy(), suffix)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_files(dir: &Path) -> Vec<String> {
+        fs::read_dir(dir).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains(".tmp-"))
+            .collect()
+    }
+
+    #[test]
+    fn replaces_contents_without_leftovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+        write(data.path(), &path, b"old").unwrap();
+        write(data.path(), &path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        // Nothing but the target is left in the user's folder
+        let names: Vec<String> = fs::read_dir(dir.path()).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["data.json"]);
+        assert!(data.path().join(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn failed_write_keeps_target_and_cleans_up() {
+        let dir = tempfile::tempdir().unwrap();
+        // Renaming a file over a non-empty directory fails on every platform
+        let path = dir.path().join("target");
+        fs::create_dir(&path).unwrap();
+        fs::write(path.join("inner"), b"keep").unwrap();
+
+        assert!(write(dir.path(), &path, b"new").is_err());
+        assert_eq!(fs::read(path.join("inner")).unwrap(), b"keep");
+        assert!(temp_files(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn concurrent_writers_leave_a_complete_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let (lock_dir, path) = (dir.path(), &path);
+                scope.spawn(move || {
+                    for _ in 0..25 {
+                        write(lock_dir, path, format!("writer-{}", i).repeat(1000).as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+        let contents = fs::read_to_string(&path).unwrap();
+        let first = &contents[..8];
+        assert_eq!(contents, first.repeat(1000));
+        assert!(temp_files(dir.path()).is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+pub mod ollama;
+pub mod openai;
+
+use crate::error::{AppError, ErrorCode};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read};
+use std::time::Duration;
+
+pub const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// Per-read timeout; a model that is still loading can stay silent for a while.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct SamplingOptions {
+    pub temperature: Option<f32>,
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub max_tokens: Option<u32>,
+    pub seed: Option<i64>,
+    pub stop: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    Ollama,
+    #[serde(rename = "openai")]
+    OpenAi,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BackendConfig {
+    pub kind: BackendKind,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ModelInfo {
+    pub id: String,
+    pub size: Option<u64>,
+    pub digest: Option<String>,
+    pub quantization: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub options: SamplingOptions,
+}
+
+// Token counts and server-side timings as reported by the backend. Fields are
+// None when the server does not report them.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Usage {
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    pub load_ms: Option<f64>,
+    pub prompt_eval_ms: Option<f64>,
+    pub eval_ms: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChatResponse {
+    pub model: String,
+    pub content: String,
+    pub finish_reason: Option<String>,
+    pub usage: Usage,
+}
+
+pub trait LlmBackend: Send + Sync {
+    fn list_models(&self) -> Result<Vec<ModelInfo>, AppError>;
+
+    fn chat(
+        &self,
+        request: &ChatRequest,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<ChatResponse, AppError>;
+}
+
+impl BackendConfig {
+    pub fn connect(&self) -> Box<dyn LlmBackend> {
+        match self.kind {
+            BackendKind::Ollama => Box::new(ollama::OllamaClient::new(self.base_url.as_deref())),
+            BackendKind::OpenAi => Box::new(openai::OpenAiClient::new(
+                self.base_url.as_deref(),
+                self.api_key.clone(),
+            )),
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct StreamEvent {
+    pub stream_id: Option<String>,
+    pub content: String,
+    pub done: bool,
+}
+
+pub fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .build()
+}
+
+pub fn join_url(base_url: &str, path: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+pub fn describe_error(base_url: &str, err: ureq::Error) -> AppError {
+    match err {
+        ureq::Error::Status(status, response) => {
+            let body = response.into_string().unwrap_or_default();
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|v| error_message(&v))
+                .unwrap_or(body);
+            let code = match status {
+                // Both servers answer 404 for an unknown model
+                404 => ErrorCode::NotFound,
+                401 | 403 => ErrorCode::PermissionDenied,
+                400 | 422 => ErrorCode::InvalidInput,
+                408 | 504 => ErrorCode::Timeout,
+                // llama.cpp answers 503 while the model is still loading
+                503 => ErrorCode::BackendUnavailable,
+                _ => ErrorCode::BackendError,
+            };
+            AppError::new(code, format!("Backend at {} returned HTTP {}: {}", base_url, status, message.trim()))
+        }
+        ureq::Error::Transport(transport) => {
+            let timed_out = std::error::Error::source(&transport)
+                .and_then(|source| source.downcast_ref::<std::io::Error>())
+                .is_some_and(|e| matches!(e.kind(), std::io::ErrorKind::TimedOut | std::io::ErrorKind::WouldBlock));
+            let code = match transport.kind() {
+                _ if timed_out => ErrorCode::Timeout,
+                ureq::ErrorKind::InvalidUrl | ureq::ErrorKind::UnknownScheme => ErrorCode::InvalidInput,
+                _ => ErrorCode::BackendUnavailable,
+            };
+            AppError::new(code, format!("Failed to reach backend at {}: {}", base_url, transport))
+        }
+    }
+}
+
+// Ollama uses {"error": "..."}, OpenAI-style servers {"error": {"message": "..."}}.
+pub fn error_message(value: &serde_json::Value) -> Option<String> {
+    match value.get("error")? {
+        serde_json::Value::String(message) => Some(message.clone()),
+        other => other.get("message")
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .or_else(|| Some(other.to_string())),
+    }
+}
+
+// Calls `on_line` for each non-empty line of a newline-delimited stream.
+pub fn for_each_line(
+    reader: impl Read,
+    mut on_line: impl FnMut(&str) -> Result<(), AppError>,
+) -> Result<(), AppError> {
+    for line in BufReader::new(reader).lines() {
+        let line = line.map_err(|e| AppError::io("Failed to read stream", e))?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        on_line(line)?;
+    }
+    Ok(())
+}
+
+// Minimal HTTP server for client tests: answers each incoming connection with
+// the next canned response and records the request line and body.
+#[cfg(test)]
+pub mod stub {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    pub struct Response {
+        pub status: u16,
+        pub content_type: &'static str,
+        pub body: String,
+    }
+
+    pub fn ok(content_type: &'static str, body: impl Into<String>) -> Response {
+        Response { status: 200, content_type, body: body.into() }
+    }
+
+    pub fn status(status: u16, body: impl Into<String>) -> Response {
+        Response { status, content_type: "application/json", body: body.into() }
+    }
+
+    pub struct Stub {
+        pub base_url: String,
+        pub requests: Arc<Mutex<Vec<(String, String)>>>,
+    }
+
+    pub fn serve(responses: Vec<Response>) -> Stub {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        std::thread::spawn(move || {
+            for response in responses {
+                let Ok((stream, _)) = listener.accept() else {
+                    return;
+                };
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                recorded.lock().unwrap().push((
+                    request_line.trim().to_string(),
+                    String::from_utf8_lossy(&body).into_owned(),
+                ));
+
+                let mut stream = reader.into_inner();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {} Stub\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    response.status,
+                    response.content_type,
+                    response.body.len(),
+                    response.body,
+                );
+            }
+        });
+
+        Stub { base_url, requests }
+    }
+
+    pub fn ndjson(lines: &[serde_json::Value]) -> Response {
+        let body: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        ok("application/x-ndjson", body.join("\n") + "\n")
+    }
+}
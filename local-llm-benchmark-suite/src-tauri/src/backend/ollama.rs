@@ -0,0 +1,607 @@
+use crate::error::AppError;
+use super::{ChatMessage, ChatRequest, ChatResponse, LlmBackend, ModelInfo, SamplingOptions, Usage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const DEFAULT_BASE_URL: &str = "http://127.0.0.1:11434";
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct OllamaModelDetails {
+    #[serde(default)]
+    pub parent_model: Option<String>,
+    #[serde(default)]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub family: Option<String>,
+    #[serde(default)]
+    pub families: Option<Vec<String>>,
+    #[serde(default)]
+    pub parameter_size: Option<String>,
+    #[serde(default)]
+    pub quantization_level: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaModel {
+    pub name: String,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub digest: Option<String>,
+    #[serde(default)]
+    pub details: OllamaModelDetails,
+}
+
+// Byte counts are summed over every layer seen so far, so `total` can still
+// grow while the manifest's layers are discovered.
+#[derive(Serialize, Clone)]
+pub struct OllamaPullProgress {
+    pub model: String,
+    pub status: String,
+    pub digest: Option<String>,
+    pub completed: u64,
+    pub total: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaShowResponse {
+    #[serde(default)]
+    pub modelfile: String,
+    #[serde(default)]
+    pub parameters: String,
+    #[serde(default)]
+    pub template: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub details: OllamaModelDetails,
+    // Architecture metadata keyed like GGUF, e.g. "llama.context_length"
+    #[serde(default)]
+    pub model_info: Option<serde_json::Map<String, serde_json::Value>>,
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaRunningModel {
+    pub name: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub size_vram: u64,
+    // Part of `size` held in system memory rather than VRAM
+    #[serde(default)]
+    pub size_ram: u64,
+    #[serde(default)]
+    pub digest: Option<String>,
+    #[serde(default)]
+    pub details: OllamaModelDetails,
+    #[serde(default)]
+    pub expires_at: Option<String>,
+    #[serde(default)]
+    pub context_length: Option<u64>,
+}
+
+// Durations are nanoseconds, as reported by Ollama on the final chunk.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct OllamaStats {
+    #[serde(default)]
+    pub total_duration: Option<u64>,
+    #[serde(default)]
+    pub load_duration: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_count: Option<u64>,
+    #[serde(default)]
+    pub prompt_eval_duration: Option<u64>,
+    #[serde(default)]
+    pub eval_count: Option<u64>,
+    #[serde(default)]
+    pub eval_duration: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaGenerateRequest {
+    pub model: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub options: SamplingOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaChatRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub options: SamplingOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct OllamaResponse {
+    pub model: String,
+    pub content: String,
+    pub done_reason: Option<String>,
+    pub stats: OllamaStats,
+}
+
+#[derive(Deserialize)]
+struct TagsResponse {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Deserialize)]
+struct PsResponse {
+    #[serde(default)]
+    models: Vec<OllamaRunningModel>,
+}
+
+#[derive(Deserialize)]
+struct PullChunk {
+    #[serde(default)]
+    status: String,
+    #[serde(default)]
+    digest: Option<String>,
+    #[serde(default)]
+    total: Option<u64>,
+    #[serde(default)]
+    completed: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    model: String,
+    #[serde(default)]
+    response: Option<String>,
+    #[serde(default)]
+    message: Option<ChatMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(flatten)]
+    stats: OllamaStats,
+}
+
+pub struct OllamaClient {
+    base_url: String,
+    agent: ureq::Agent,
+}
+
+impl OllamaClient {
+    pub fn new(base_url: Option<&str>) -> Self {
+        Self {
+            base_url: base_url.unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/').to_string(),
+            agent: super::agent(),
+        }
+    }
+
+    pub fn list_models(&self) -> Result<Vec<OllamaModel>, AppError> {
+        let response = self.agent.get(&super::join_url(&self.base_url, "/api/tags"))
+            .call()
+            .map_err(|e| super::describe_error(&self.base_url, e))?;
+
+        let tags: TagsResponse = response.into_json()
+            .map_err(|e| AppError::backend(format!("Failed to parse model list: {}", e)))?;
+        Ok(tags.models)
+    }
+
+    pub fn show(&self, model: &str) -> Result<OllamaShowResponse, AppError> {
+        let response = self.agent.post(&super::join_url(&self.base_url, "/api/show"))
+            .send_json(serde_json::json!({ "model": model }))
+            .map_err(|e| super::describe_error(&self.base_url, e))?;
+
+        response.into_json()
+            .map_err(|e| AppError::backend(format!("Failed to parse model details: {}", e)))
+    }
+
+    pub fn running_models(&self) -> Result<Vec<OllamaRunningModel>, AppError> {
+        let response = self.agent.get(&super::join_url(&self.base_url, "/api/ps"))
+            .call()
+            .map_err(|e| super::describe_error(&self.base_url, e))?;
+
+        let ps: PsResponse = response.into_json()
+            .map_err(|e| AppError::backend(format!("Failed to parse running models: {}", e)))?;
+        Ok(ps.models
+            .into_iter()
+            .map(|m| OllamaRunningModel { size_ram: m.size.saturating_sub(m.size_vram), ..m })
+            .collect())
+    }
+
+    pub fn delete(&self, model: &str) -> Result<(), AppError> {
+        self.agent.delete(&super::join_url(&self.base_url, "/api/delete"))
+            .send_json(serde_json::json!({ "model": model }))
+            .map_err(|e| super::describe_error(&self.base_url, e))?;
+        Ok(())
+    }
+
+    pub fn pull(
+        &self,
+        model: &str,
+        mut on_progress: impl FnMut(OllamaPullProgress),
+    ) -> Result<(), AppError> {
+        let response = self.agent.post(&super::join_url(&self.base_url, "/api/pull"))
+            .send_json(serde_json::json!({ "model": model, "stream": true }))
+            .map_err(|e| super::describe_error(&self.base_url, e))?;
+
+        // (completed, total) per layer digest
+        let mut layers: HashMap<String, (u64, u64)> = HashMap::new();
+        let mut succeeded = false;
+
+        super::for_each_line(response.into_reader(), |line| {
+            let value: serde_json::Value = serde_json::from_str(line)
+                .map_err(|e| AppError::backend(format!("Failed to parse pull progress: {}", e)))?;
+            if let Some(message) = super::error_message(&value) {
+                return Err(AppError::backend(format!("Ollama error: {}", message)));
+            }
+            let chunk: PullChunk = serde_json::from_value(value)
+                .map_err(|e| AppError::backend(format!("Failed to parse pull progress: {}", e)))?;
+
+            if let (Some(digest), Some(total)) = (&chunk.digest, chunk.total) {
+                layers.insert(digest.clone(), (chunk.completed.unwrap_or(0), total));
+            }
+            succeeded = chunk.status == "success";
+            on_progress(OllamaPullProgress {
+                model: model.to_string(),
+                status: chunk.status,
+                digest: chunk.digest,
+                completed: layers.values().map(|(completed, _)| completed).sum(),
+                total: layers.values().map(|(_, total)| total).sum(),
+            });
+            Ok(())
+        })?;
+
+        if !succeeded {
+            return Err(AppError::backend(format!("Pull of {} ended before it succeeded", model)));
+        }
+        Ok(())
+    }
+
+    pub fn generate(
+        &self,
+        request: &OllamaGenerateRequest,
+        on_token: impl FnMut(&str),
+    ) -> Result<OllamaResponse, AppError> {
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "prompt": request.prompt,
+            "options": options_json(&request.options),
+            "stream": true,
+        });
+        if let Some(system) = &request.system {
+            body["system"] = system.clone().into();
+        }
+        self.stream("/api/generate", body, on_token)
+    }
+
+    pub fn chat(
+        &self,
+        request: &OllamaChatRequest,
+        on_token: impl FnMut(&str),
+    ) -> Result<OllamaResponse, AppError> {
+        let body = serde_json::json!({
+            "model": request.model,
+            "messages": request.messages,
+            "options": options_json(&request.options),
+            "stream": true,
+        });
+        self.stream("/api/chat", body, on_token)
+    }
+
+    fn stream(
+        &self,
+        path: &str,
+        body: serde_json::Value,
+        mut on_token: impl FnMut(&str),
+    ) -> Result<OllamaResponse, AppError> {
+        let response = self.agent.post(&super::join_url(&self.base_url, path))
+            .send_json(body)
+            .map_err(|e| super::describe_error(&self.base_url, e))?;
+
+        let mut result = OllamaResponse {
+            model: String::new(),
+            content: String::new(),
+            done_reason: None,
+            stats: OllamaStats::default(),
+        };
+        let mut finished = false;
+
+        super::for_each_line(response.into_reader(), |line| {
+            let value: serde_json::Value = serde_json::from_str(line)
+                .map_err(|e| AppError::backend(format!("Failed to parse stream chunk: {}", e)))?;
+            if let Some(message) = super::error_message(&value) {
+                return Err(AppError::backend(format!("Ollama error: {}", message)));
+            }
+            let chunk: StreamChunk = serde_json::from_value(value)
+                .map_err(|e| AppError::backend(format!("Failed to parse stream chunk: {}", e)))?;
+
+            let token = chunk.response
+                .or(chunk.message.map(|m| m.content))
+                .unwrap_or_default();
+            if !token.is_empty() {
+                on_token(&token);
+                result.content.push_str(&token);
+            }
+            if !chunk.model.is_empty() {
+                result.model = chunk.model;
+            }
+            if chunk.done {
+                result.done_reason = chunk.done_reason;
+                result.stats = chunk.stats;
+                finished = true;
+            }
+            Ok(())
+        })?;
+
+        if !finished {
+            return Err(AppError::backend("Ollama stream ended before the final chunk"));
+        }
+        Ok(result)
+    }
+}
+
+impl LlmBackend for OllamaClient {
+    fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        Ok(OllamaClient::list_models(self)?
+            .into_iter()
+            .map(|m| ModelInfo {
+                id: m.name,
+                size: Some(m.size),
+                digest: m.digest,
+                quantization: m.details.quantization_level,
+            })
+            .collect())
+    }
+
+    fn chat(
+        &self,
+        request: &ChatRequest,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<ChatResponse, AppError> {
+        let request = OllamaChatRequest {
+            model: request.model.clone(),
+            messages: request.messages.clone(),
+            options: request.options.clone(),
+        };
+        let response = OllamaClient::chat(self, &request, on_token)?;
+
+        let ms = |ns: Option<u64>| ns.map(|ns| ns as f64 / 1_000_000.0);
+        Ok(ChatResponse {
+            model: response.model,
+            content: response.content,
+            finish_reason: response.done_reason,
+            usage: Usage {
+                prompt_tokens: response.stats.prompt_eval_count,
+                completion_tokens: response.stats.eval_count,
+                load_ms: ms(response.stats.load_duration),
+                prompt_eval_ms: ms(response.stats.prompt_eval_duration),
+                eval_ms: ms(response.stats.eval_duration),
+            },
+        })
+    }
+}
+
+fn options_json(options: &SamplingOptions) -> serde_json::Value {
+    let mut map = serde_json::Map::new();
+    if let Some(v) = options.temperature {
+        map.insert("temperature".to_string(), v.into());
+    }
+    if let Some(v) = options.top_p {
+        map.insert("top_p".to_string(), v.into());
+    }
+    if let Some(v) = options.top_k {
+        map.insert("top_k".to_string(), v.into());
+    }
+    if let Some(v) = options.max_tokens {
+        map.insert("num_predict".to_string(), v.into());
+    }
+    if let Some(v) = options.seed {
+        map.insert("seed".to_string(), v.into());
+    }
+    if let Some(v) = &options.stop {
+        map.insert("stop".to_string(), v.clone().into());
+    }
+    serde_json::Value::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::stub::{self, ndjson};
+    use serde_json::json;
+
+    fn chat_request() -> OllamaChatRequest {
+        OllamaChatRequest {
+            model: "llama3".to_string(),
+            messages: vec![ChatMessage { role: "user".to_string(), content: "hi".to_string() }],
+            options: SamplingOptions { max_tokens: Some(8), ..Default::default() },
+        }
+    }
+
+    #[test]
+    fn list_models_parses_tags() {
+        let server = stub::serve(vec![stub::ok("application/json", json!({
+            "models": [{"name": "llama3:8b", "size": 123, "digest": "abc", "details": {"quantization_level": "Q4_K_M"}}]
+        }).to_string())]);
+        let models = OllamaClient::new(Some(&server.base_url)).list_models().unwrap();
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].name, "llama3:8b");
+        assert_eq!(models[0].details.quantization_level.as_deref(), Some("Q4_K_M"));
+    }
+
+    #[test]
+    fn chat_streams_tokens_and_final_stats() {
+        let server = stub::serve(vec![ndjson(&[
+            json!({"model": "llama3", "message": {"role": "assistant", "content": "Hel"}, "done": false}),
+            json!({"model": "llama3", "message": {"role": "assistant", "content": "lo"}, "done": false}),
+            json!({"model": "llama3", "done": true, "done_reason": "stop", "prompt_eval_count": 12,
+                   "prompt_eval_duration": 1_000_000, "eval_count": 2, "eval_duration": 3_000_000}),
+        ])]);
+
+        let mut tokens = Vec::new();
+        let response = OllamaClient::new(Some(&server.base_url))
+            .chat(&chat_request(), |t| tokens.push(t.to_string()))
+            .unwrap();
+
+        assert_eq!(tokens, ["Hel", "lo"]);
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.done_reason.as_deref(), Some("stop"));
+        assert_eq!(response.stats.prompt_eval_count, Some(12));
+        assert_eq!(response.stats.eval_duration, Some(3_000_000));
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests[0].0, "POST /api/chat HTTP/1.1");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].1).unwrap();
+        assert_eq!(body["options"]["num_predict"], 8);
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn mid_stream_error_is_reported() {
+        let server = stub::serve(vec![ndjson(&[
+            json!({"model": "llama3", "response": "Hel", "done": false}),
+            json!({"error": "model ran out of memory"}),
+        ])]);
+        let request = OllamaGenerateRequest {
+            model: "llama3".to_string(),
+            prompt: "hi".to_string(),
+            system: None,
+            options: SamplingOptions::default(),
+        };
+        let err = OllamaClient::new(Some(&server.base_url)).generate(&request, |_| {}).err().unwrap();
+        assert_eq!(err.code, crate::error::ErrorCode::BackendError);
+        assert!(err.message.contains("model ran out of memory"));
+    }
+
+    #[test]
+    fn stream_without_done_is_an_error() {
+        let server = stub::serve(vec![ndjson(&[
+            json!({"model": "llama3", "message": {"role": "assistant", "content": "Hel"}, "done": false}),
+        ])]);
+        let err = OllamaClient::new(Some(&server.base_url)).chat(&chat_request(), |_| {}).err().unwrap();
+        assert!(err.message.contains("ended before the final chunk"));
+    }
+
+    #[test]
+    fn unknown_model_maps_to_not_found() {
+        let server = stub::serve(vec![stub::status(404, json!({"error": "model 'llama3' not found"}).to_string())]);
+        let err = OllamaClient::new(Some(&server.base_url)).chat(&chat_request(), |_| {}).err().unwrap();
+        assert_eq!(err.code, crate::error::ErrorCode::NotFound);
+        assert!(err.message.contains("model 'llama3' not found"));
+    }
+
+    #[test]
+    fn pull_sums_progress_across_layers() {
+        let server = stub::serve(vec![ndjson(&[
+            json!({"status": "pulling manifest"}),
+            json!({"status": "pulling a", "digest": "sha256:a", "total": 1000, "completed": 400}),
+            json!({"status": "pulling b", "digest": "sha256:b", "total": 200, "completed": 50}),
+            json!({"status": "pulling a", "digest": "sha256:a", "total": 1000, "completed": 1000}),
+            json!({"status": "verifying sha256 digest"}),
+            json!({"status": "success"}),
+        ])]);
+
+        let mut progress = Vec::new();
+        OllamaClient::new(Some(&server.base_url))
+            .pull("llama3", |p| progress.push((p.status, p.completed, p.total)))
+            .unwrap();
+
+        assert_eq!(progress[0], ("pulling manifest".to_string(), 0, 0));
+        assert_eq!((progress[1].1, progress[1].2), (400, 1000));
+        assert_eq!((progress[2].1, progress[2].2), (450, 1200));
+        assert_eq!((progress[3].1, progress[3].2), (1050, 1200));
+        assert_eq!(progress.last().unwrap(), &("success".to_string(), 1050, 1200));
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests[0].0, "POST /api/pull HTTP/1.1");
+        let body: serde_json::Value = serde_json::from_str(&requests[0].1).unwrap();
+        assert_eq!(body["model"], "llama3");
+    }
+
+    #[test]
+    fn pull_that_stops_early_is_an_error() {
+        let server = stub::serve(vec![ndjson(&[
+            json!({"status": "pulling a", "digest": "sha256:a", "total": 1000, "completed": 400}),
+        ])]);
+        let err = OllamaClient::new(Some(&server.base_url)).pull("llama3", |_| {}).err().unwrap();
+        assert_eq!(err.code, crate::error::ErrorCode::BackendError);
+        assert!(err.message.contains("Pull of llama3 ended before it succeeded"));
+    }
+
+    #[test]
+    fn running_models_split_ram_and_vram() {
+        let server = stub::serve(vec![stub::ok("application/json", json!({
+            "models": [
+                {"name": "llama3:8b", "size": 6_000, "size_vram": 4_500},
+                {"name": "phi3:mini", "size": 2_000, "size_vram": 2_000},
+                {"name": "odd:1b", "size": 100, "size_vram": 150},
+            ]
+        }).to_string())]);
+        let models = OllamaClient::new(Some(&server.base_url)).running_models().unwrap();
+        let size_ram: Vec<u64> = models.iter().map(|m| m.size_ram).collect();
+        assert_eq!(size_ram, [1_500, 0, 0]);
+    }
+
+    #[test]
+    fn show_parses_model_details() {
+        let server = stub::serve(vec![stub::ok("application/json", json!({
+            "modelfile": "FROM /models/llama3.gguf\nPARAMETER temperature 0.7\n",
+            "parameters": "temperature 0.7\nstop \"<|eot_id|>\"",
+            "template": "{{ .System }}\n{{ .Prompt }}",
+            "license": "META LLAMA 3 COMMUNITY LICENSE",
+            "details": {
+                "parent_model": "",
+                "format": "gguf",
+                "family": "llama",
+                "families": ["llama"],
+                "parameter_size": "8.0B",
+                "quantization_level": "Q4_K_M",
+            },
+            "model_info": {"general.architecture": "llama", "llama.context_length": 8192},
+            "capabilities": ["completion"],
+            "modified_at": "2024-05-01T10:00:00Z",
+        }).to_string())]);
+
+        let show = OllamaClient::new(Some(&server.base_url)).show("llama3:8b").unwrap();
+        assert!(show.modelfile.starts_with("FROM /models/llama3.gguf"));
+        assert_eq!(show.parameters, "temperature 0.7\nstop \"<|eot_id|>\"");
+        assert_eq!(show.template, "{{ .System }}\n{{ .Prompt }}");
+        assert_eq!(show.system, None);
+        assert_eq!(show.details.format.as_deref(), Some("gguf"));
+        assert_eq!(show.details.family.as_deref(), Some("llama"));
+        assert_eq!(show.details.families.as_deref(), Some(&["llama".to_string()][..]));
+        assert_eq!(show.details.parameter_size.as_deref(), Some("8.0B"));
+        assert_eq!(show.details.quantization_level.as_deref(), Some("Q4_K_M"));
+        assert_eq!(show.model_info.unwrap()["llama.context_length"], 8192);
+        assert_eq!(show.capabilities, ["completion"]);
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests[0].0, "POST /api/show HTTP/1.1");
+        assert_eq!(requests[0].1, json!({"model": "llama3:8b"}).to_string());
+    }
+
+    #[test]
+    fn show_and_delete_of_unknown_model_map_to_not_found() {
+        let missing = || stub::status(404, json!({"error": "model 'gone' not found"}).to_string());
+        let server = stub::serve(vec![missing(), missing()]);
+        let client = OllamaClient::new(Some(&server.base_url));
+
+        assert_eq!(client.show("gone").err().unwrap().code, crate::error::ErrorCode::NotFound);
+        assert_eq!(client.delete("gone").err().unwrap().code, crate::error::ErrorCode::NotFound);
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests[0].0, "POST /api/show HTTP/1.1");
+        assert_eq!(requests[1].0, "DELETE /api/delete HTTP/1.1");
+        assert_eq!(requests[1].1, json!({"model": "gone"}).to_string());
+    }
+}
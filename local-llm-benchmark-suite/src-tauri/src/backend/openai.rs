@@ -0,0 +1,312 @@
+use crate::error::AppError;
+use super::{ChatRequest, ChatResponse, LlmBackend, ModelInfo, Usage};
+use serde::Deserialize;
+
+// llama.cpp's llama-server default; LM Studio and vLLM users pass their own.
+pub const DEFAULT_BASE_URL: &str = "http://127.0.0.1:8080/v1";
+
+#[derive(Deserialize)]
+struct ModelsResponse {
+    #[serde(default)]
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct StreamChunk {
+    #[serde(default)]
+    model: Option<String>,
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    #[serde(default)]
+    usage: Option<UsageChunk>,
+    // llama.cpp extension with server-side timings
+    #[serde(default)]
+    timings: Option<Timings>,
+}
+
+#[derive(Deserialize)]
+struct StreamChoice {
+    #[serde(default)]
+    delta: Option<Delta>,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Delta {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UsageChunk {
+    #[serde(default)]
+    prompt_tokens: Option<u64>,
+    #[serde(default)]
+    completion_tokens: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct Timings {
+    #[serde(default)]
+    prompt_n: Option<u64>,
+    #[serde(default)]
+    prompt_ms: Option<f64>,
+    #[serde(default)]
+    predicted_n: Option<u64>,
+    #[serde(default)]
+    predicted_ms: Option<f64>,
+}
+
+pub struct OpenAiClient {
+    base_url: String,
+    api_key: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl OpenAiClient {
+    pub fn new(base_url: Option<&str>, api_key: Option<String>) -> Self {
+        Self {
+            base_url: base_url.unwrap_or(DEFAULT_BASE_URL).trim_end_matches('/').to_string(),
+            api_key: api_key.filter(|k| !k.is_empty()),
+            agent: super::agent(),
+        }
+    }
+
+    fn authorize(&self, request: ureq::Request) -> ureq::Request {
+        match &self.api_key {
+            Some(key) => request.set("Authorization", &format!("Bearer {}", key)),
+            None => request,
+        }
+    }
+}
+
+impl LlmBackend for OpenAiClient {
+    fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+        let request = self.agent.get(&super::join_url(&self.base_url, "/models"));
+        let response = self.authorize(request)
+            .call()
+            .map_err(|e| super::describe_error(&self.base_url, e))?;
+
+        let models: ModelsResponse = response.into_json()
+            .map_err(|e| AppError::backend(format!("Failed to parse model list: {}", e)))?;
+        Ok(models.data.into_iter()
+            .map(|m| ModelInfo {
+                id: m.id,
+                size: None,
+                digest: None,
+                quantization: None,
+            })
+            .collect())
+    }
+
+    fn chat(
+        &self,
+        request: &ChatRequest,
+        on_token: &mut dyn FnMut(&str),
+    ) -> Result<ChatResponse, AppError> {
+        let options = &request.options;
+        let mut body = serde_json::json!({
+            "model": request.model,
+            "messages": request.messages,
+            "stream": true,
+            "stream_options": { "include_usage": true },
+        });
+        if let Some(v) = options.temperature {
+            body["temperature"] = v.into();
+        }
+        if let Some(v) = options.top_p {
+            body["top_p"] = v.into();
+        }
+        // Not part of the OpenAI API, but llama.cpp, vLLM and LM Studio honor it
+        if let Some(v) = options.top_k {
+            body["top_k"] = v.into();
+        }
+        if let Some(v) = options.max_tokens {
+            body["max_tokens"] = v.into();
+        }
+        if let Some(v) = options.seed {
+            body["seed"] = v.into();
+        }
+        if let Some(v) = &options.stop {
+            body["stop"] = v.clone().into();
+        }
+
+        let post = self.agent.post(&super::join_url(&self.base_url, "/chat/completions"));
+        let response = self.authorize(post)
+            .send_json(body)
+            .map_err(|e| super::describe_error(&self.base_url, e))?;
+
+        let mut result = ChatResponse {
+            model: request.model.clone(),
+            content: String::new(),
+            finish_reason: None,
+            usage: Usage::default(),
+        };
+        let mut finished = false;
+
+        super::for_each_line(response.into_reader(), |line| {
+            // Server-sent events: only `data:` lines carry payloads
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                return Ok(());
+            };
+            if data == "[DONE]" {
+                finished = true;
+                return Ok(());
+            }
+
+            let value: serde_json::Value = serde_json::from_str(data)
+                .map_err(|e| AppError::backend(format!("Failed to parse stream chunk: {}", e)))?;
+            if let Some(message) = super::error_message(&value) {
+                return Err(AppError::backend(format!("Backend error: {}", message)));
+            }
+            let chunk: StreamChunk = serde_json::from_value(value)
+                .map_err(|e| AppError::backend(format!("Failed to parse stream chunk: {}", e)))?;
+
+            if let Some(model) = chunk.model.filter(|m| !m.is_empty()) {
+                result.model = model;
+            }
+            for choice in chunk.choices {
+                if let Some(token) = choice.delta.and_then(|d| d.content).filter(|t| !t.is_empty()) {
+                    on_token(&token);
+                    result.content.push_str(&token);
+                }
+                if choice.finish_reason.is_some() {
+                    result.finish_reason = choice.finish_reason;
+                }
+            }
+            if let Some(usage) = chunk.usage {
+                result.usage.prompt_tokens = usage.prompt_tokens.or(result.usage.prompt_tokens);
+                result.usage.completion_tokens = usage.completion_tokens.or(result.usage.completion_tokens);
+            }
+            if let Some(timings) = chunk.timings {
+                result.usage.prompt_tokens = result.usage.prompt_tokens.or(timings.prompt_n);
+                result.usage.completion_tokens = result.usage.completion_tokens.or(timings.predicted_n);
+                result.usage.prompt_eval_ms = timings.prompt_ms;
+                result.usage.eval_ms = timings.predicted_ms;
+            }
+            Ok(())
+        })?;
+
+        if !finished && result.finish_reason.is_none() {
+            return Err(AppError::backend("Backend stream ended before completion"));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::stub;
+    use crate::backend::{ChatMessage, SamplingOptions};
+    use crate::error::ErrorCode;
+    use serde_json::json;
+
+    fn chat_request() -> ChatRequest {
+        ChatRequest {
+            model: "local".to_string(),
+            messages: vec![ChatMessage { role: "user".to_string(), content: "hi".to_string() }],
+            options: SamplingOptions { max_tokens: Some(8), top_k: Some(40), ..Default::default() },
+        }
+    }
+
+    // One `data:` event per payload, as a server-sent event stream
+    fn sse(events: &[&str]) -> stub::Response {
+        let body: String = events.iter().map(|e| format!("data: {}\n\n", e)).collect();
+        stub::ok("text/event-stream", body)
+    }
+
+    fn delta(content: &str) -> String {
+        json!({"model": "qwen2.5-7b", "choices": [{"delta": {"content": content}, "finish_reason": null}]}).to_string()
+    }
+
+    #[test]
+    fn chat_parses_the_event_stream() {
+        let first = delta("Hel");
+        let second = delta("lo");
+        let finish = json!({"choices": [{"delta": {}, "finish_reason": "length"}]}).to_string();
+        // llama.cpp sends its timings with the final chunk, usage comes last
+        let timings = json!({"choices": [], "timings": {
+            "prompt_n": 99, "prompt_ms": 12.5, "predicted_n": 2, "predicted_ms": 40.0,
+        }}).to_string();
+        let usage = json!({"choices": [], "usage": {"prompt_tokens": 7}}).to_string();
+        let body = format!(
+            ": keep-alive\n\nevent: message\ndata: {}\n\ndata: {}\n\ndata: {}\n\ndata: {}\n\ndata: {}\n\ndata: [DONE]\n\n",
+            first, second, finish, timings, usage,
+        );
+        let server = stub::serve(vec![stub::ok("text/event-stream", body)]);
+
+        let mut tokens = Vec::new();
+        let response = OpenAiClient::new(Some(&server.base_url), None)
+            .chat(&chat_request(), &mut |t| tokens.push(t.to_string()))
+            .unwrap();
+
+        assert_eq!(tokens, ["Hel", "lo"]);
+        assert_eq!(response.content, "Hello");
+        assert_eq!(response.model, "qwen2.5-7b");
+        assert_eq!(response.finish_reason.as_deref(), Some("length"));
+        // Reported usage wins over timings; timings fill in the rest
+        assert_eq!(response.usage.prompt_tokens, Some(7));
+        assert_eq!(response.usage.completion_tokens, Some(2));
+        assert_eq!(response.usage.prompt_eval_ms, Some(12.5));
+        assert_eq!(response.usage.eval_ms, Some(40.0));
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests[0].0, "POST /chat/completions HTTP/1.1");
+        let sent: serde_json::Value = serde_json::from_str(&requests[0].1).unwrap();
+        assert_eq!(sent["stream"], true);
+        assert_eq!(sent["stream_options"]["include_usage"], true);
+        assert_eq!(sent["max_tokens"], 8);
+        assert_eq!(sent["top_k"], 40);
+        assert!(sent.get("temperature").is_none());
+    }
+
+    #[test]
+    fn done_marker_alone_completes_the_stream() {
+        let server = stub::serve(vec![sse(&[&delta("ok"), "[DONE]"])]);
+        let response = OpenAiClient::new(Some(&server.base_url), None)
+            .chat(&chat_request(), &mut |_| {})
+            .unwrap();
+        assert_eq!(response.content, "ok");
+        assert_eq!(response.finish_reason, None);
+        assert_eq!(response.usage.completion_tokens, None);
+    }
+
+    #[test]
+    fn error_event_mid_stream_fails_the_chat() {
+        let error = json!({"error": {"message": "context size exceeded", "type": "server_error"}}).to_string();
+        let server = stub::serve(vec![sse(&[&delta("partial"), &error, &delta("ignored"), "[DONE]"])]);
+
+        let mut tokens = Vec::new();
+        let err = OpenAiClient::new(Some(&server.base_url), None)
+            .chat(&chat_request(), &mut |t| tokens.push(t.to_string()))
+            .err()
+            .unwrap();
+        assert!(matches!(err.code, ErrorCode::BackendError));
+        assert!(err.message.contains("context size exceeded"), "{}", err.message);
+        assert_eq!(tokens, ["partial"]);
+    }
+
+    #[test]
+    fn truncated_stream_is_an_error() {
+        let server = stub::serve(vec![sse(&[&delta("cut")])]);
+        let err = OpenAiClient::new(Some(&server.base_url), None)
+            .chat(&chat_request(), &mut |_| {})
+            .err()
+            .unwrap();
+        assert!(err.message.contains("ended before completion"), "{}", err.message);
+
+        let server = stub::serve(vec![sse(&["{not json"])]);
+        let err = OpenAiClient::new(Some(&server.base_url), None)
+            .chat(&chat_request(), &mut |_| {})
+            .err()
+            .unwrap();
+        assert!(err.message.contains("Failed to parse stream chunk"), "{}", err.message);
+    }
+}
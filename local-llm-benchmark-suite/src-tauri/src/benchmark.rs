@@ -0,0 +1,391 @@
+use crate::backend::{BackendConfig, BackendKind, ChatMessage, ChatRequest, LlmBackend, SamplingOptions, Usage};
+use crate::error::AppError;
+use crate::metrics::{unix_millis, MetricsSample, MetricsState};
+use crate::resources::{self, ResourceUsage};
+use crate::server::ServerConfig;
+use crate::stats;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+// Progress events are throttled so long generations do not flood the webview.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+static RUN_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BenchmarkRequest {
+    pub backend: BackendConfig,
+    pub model: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub options: SamplingOptions,
+    #[serde(default)]
+    pub run_id: Option<String>,
+    // Local weights file to pin the result to; see `model_digest` in main.rs
+    #[serde(default)]
+    pub model_path: Option<String>,
+    // Inference server launched for this run and torn down afterwards
+    #[serde(default)]
+    pub server: Option<ServerConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LatencyDistribution {
+    pub count: usize,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BenchmarkResult {
+    pub run_id: String,
+    pub backend: BackendKind,
+    pub model: String,
+    pub started_at: u64,
+    pub time_to_first_token_ms: Option<f64>,
+    pub total_latency_ms: f64,
+    pub inter_token_latency: LatencyDistribution,
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    // True when the backend reported no completion token count and the
+    // generation rate was computed from the number of streamed chunks
+    #[serde(default)]
+    pub completion_tokens_estimated: bool,
+    pub prompt_tokens_per_second: Option<f64>,
+    pub generation_tokens_per_second: Option<f64>,
+    pub output_chunks: usize,
+    pub output_chars: usize,
+    pub output: String,
+    pub finish_reason: Option<String>,
+    pub usage: Usage,
+    pub resources: Option<ResourceUsage>,
+    #[serde(default)]
+    pub model_digest: Option<String>,
+    // Raw samples behind `resources`, persisted separately from the result
+    #[serde(skip)]
+    pub samples: Vec<MetricsSample>,
+}
+
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchmarkPhase {
+    Started,
+    FirstToken,
+    Generating,
+    Finished,
+    Failed,
+}
+
+#[derive(Serialize, Clone)]
+pub struct BenchmarkProgress {
+    pub run_id: String,
+    pub phase: BenchmarkPhase,
+    pub elapsed_ms: f64,
+    pub chunks: usize,
+    pub error: Option<String>,
+}
+
+pub fn new_run_id() -> String {
+    format!("run-{}-{}", unix_millis(), RUN_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+pub fn run(
+    request: &BenchmarkRequest,
+    on_progress: &mut dyn FnMut(BenchmarkProgress),
+) -> Result<BenchmarkResult, AppError> {
+    let backend = request.backend.connect();
+    run_with(backend.as_ref(), request, on_progress)
+}
+
+// Runs the benchmark with the metrics sampler enabled and attaches the
+// resource usage observed between the run's start and end.
+pub fn run_monitored(
+    metrics: &MetricsState,
+    request: &BenchmarkRequest,
+    on_progress: &mut dyn FnMut(BenchmarkProgress),
+) -> Result<BenchmarkResult, AppError> {
+    let sampling = metrics.hold_sampling();
+
+    let baseline = metrics.snapshot();
+    let start = Instant::now();
+    let result = run(request, on_progress);
+    let end = Instant::now();
+
+    let mut samples = metrics.samples_between(start, end).unwrap_or_default();
+    if samples.is_empty() {
+        // Runs shorter than the sampling interval still get one reading
+        if let Ok(metrics) = metrics.snapshot() {
+            samples.push(MetricsSample {
+                instant: end,
+                timestamp: unix_millis(),
+                metrics,
+                process: None,
+            });
+        }
+    }
+    drop(sampling);
+
+    let mut result = result?;
+    result.resources = baseline.ok().map(|baseline| resources::summarize(&baseline, &samples));
+    result.samples = samples;
+    Ok(result)
+}
+
+pub fn run_with(
+    backend: &dyn LlmBackend,
+    request: &BenchmarkRequest,
+    on_progress: &mut dyn FnMut(BenchmarkProgress),
+) -> Result<BenchmarkResult, AppError> {
+    let run_id = request.run_id.clone().unwrap_or_else(new_run_id);
+
+    let mut messages = Vec::new();
+    if let Some(system) = request.system.as_ref().filter(|s| !s.is_empty()) {
+        messages.push(ChatMessage { role: "system".to_string(), content: system.clone() });
+    }
+    messages.push(ChatMessage { role: "user".to_string(), content: request.prompt.clone() });
+    let chat = ChatRequest {
+        model: request.model.clone(),
+        messages,
+        options: request.options.clone(),
+    };
+
+    let progress = |phase, elapsed: Duration, chunks, error| BenchmarkProgress {
+        run_id: run_id.clone(),
+        phase,
+        elapsed_ms: ms(elapsed),
+        chunks,
+        error,
+    };
+
+    let started_at = unix_millis();
+    let start = Instant::now();
+    on_progress(progress(BenchmarkPhase::Started, Duration::ZERO, 0, None));
+
+    // Arrival time of every streamed chunk, relative to `start`
+    let mut arrivals: Vec<Duration> = Vec::new();
+    let mut last_progress = start;
+    let response = backend.chat(&chat, &mut |_token| {
+        let now = Instant::now();
+        arrivals.push(now - start);
+
+        if arrivals.len() == 1 {
+            on_progress(progress(BenchmarkPhase::FirstToken, now - start, 1, None));
+            last_progress = now;
+        } else if now - last_progress >= PROGRESS_INTERVAL {
+            on_progress(progress(BenchmarkPhase::Generating, now - start, arrivals.len(), None));
+            last_progress = now;
+        }
+    });
+    let total = start.elapsed();
+
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            on_progress(progress(BenchmarkPhase::Failed, total, arrivals.len(), Some(e.message.clone())));
+            return Err(e);
+        }
+    };
+
+    let time_to_first_token = arrivals.first().copied();
+    let gaps: Vec<f64> = arrivals.windows(2)
+        .map(|w| ms(w[1] - w[0]))
+        .collect();
+
+    let usage = response.usage.clone();
+    // Prefer server-reported timings; fall back to what was observed on the wire.
+    let prompt_tokens_per_second = match (usage.prompt_tokens, usage.prompt_eval_ms, time_to_first_token) {
+        (Some(n), Some(eval_ms), _) if eval_ms > 0.0 => Some(n as f64 / (eval_ms / 1000.0)),
+        (Some(n), None, Some(ttft)) if !ttft.is_zero() => Some(n as f64 / ttft.as_secs_f64()),
+        _ => None,
+    };
+    // Without a reported count each streamed chunk is taken to be one token
+    let completion_tokens_estimated = usage.completion_tokens.is_none();
+    let generated = usage.completion_tokens.unwrap_or(arrivals.len() as u64);
+    let generation_window = time_to_first_token.map(|ttft| total - ttft);
+    let generation_tokens_per_second = match (usage.eval_ms, generation_window) {
+        (Some(eval_ms), _) if eval_ms > 0.0 => Some(generated as f64 / (eval_ms / 1000.0)),
+        (_, Some(window)) if !window.is_zero() && generated > 1 => {
+            // The first token is produced by prompt processing, not generation
+            Some((generated - 1) as f64 / window.as_secs_f64())
+        }
+        _ => None,
+    };
+
+    on_progress(progress(BenchmarkPhase::Finished, total, arrivals.len(), None));
+
+    Ok(BenchmarkResult {
+        run_id: run_id.clone(),
+        backend: request.backend.kind,
+        model: response.model,
+        started_at,
+        time_to_first_token_ms: time_to_first_token.map(ms),
+        total_latency_ms: ms(total),
+        inter_token_latency: distribution(&gaps),
+        prompt_tokens: usage.prompt_tokens,
+        completion_tokens: usage.completion_tokens,
+        completion_tokens_estimated,
+        prompt_tokens_per_second,
+        generation_tokens_per_second,
+        output_chunks: arrivals.len(),
+        output_chars: response.content.chars().count(),
+        output: response.content,
+        finish_reason: response.finish_reason,
+        usage,
+        resources: None,
+        model_digest: None,
+        samples: Vec::new(),
+    })
+}
+
+fn ms(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+pub fn distribution(values_ms: &[f64]) -> LatencyDistribution {
+    if values_ms.is_empty() {
+        return LatencyDistribution::default();
+    }
+
+    let mut sorted = values_ms.to_vec();
+    sorted.sort_by(f64::total_cmp);
+
+    LatencyDistribution {
+        count: sorted.len(),
+        mean_ms: sorted.iter().sum::<f64>() / sorted.len() as f64,
+        p50_ms: stats::percentile(&sorted, 50.0),
+        p90_ms: stats::percentile(&sorted, 90.0),
+        p99_ms: stats::percentile(&sorted, 99.0),
+        min_ms: sorted[0],
+        max_ms: sorted[sorted.len() - 1],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{ChatResponse, ModelInfo};
+    use crate::error::ErrorCode;
+
+    // Streams `chunks` with a fixed delay before each one, then fails or
+    // returns `usage`
+    struct FakeBackend {
+        delay: Duration,
+        chunks: usize,
+        usage: Usage,
+        fail: bool,
+    }
+
+    impl LlmBackend for FakeBackend {
+        fn list_models(&self) -> Result<Vec<ModelInfo>, AppError> {
+            Ok(Vec::new())
+        }
+
+        fn chat(&self, request: &ChatRequest, on_token: &mut dyn FnMut(&str)) -> Result<ChatResponse, AppError> {
+            let mut content = String::new();
+            for _ in 0..self.chunks {
+                std::thread::sleep(self.delay);
+                on_token("ab");
+                content.push_str("ab");
+            }
+            if self.fail {
+                return Err(AppError::backend("connection reset mid-stream"));
+            }
+            Ok(ChatResponse {
+                model: request.model.clone(),
+                content,
+                finish_reason: Some("stop".to_string()),
+                usage: self.usage.clone(),
+            })
+        }
+    }
+
+    fn request() -> BenchmarkRequest {
+        BenchmarkRequest {
+            backend: BackendConfig { kind: BackendKind::Ollama, base_url: None, api_key: None },
+            model: "fake".to_string(),
+            prompt: "hi".to_string(),
+            system: None,
+            options: SamplingOptions::default(),
+            run_id: Some("run-test".to_string()),
+            model_path: None,
+            server: None,
+        }
+    }
+
+    fn backend(chunks: usize, usage: Usage) -> FakeBackend {
+        FakeBackend { delay: Duration::from_millis(20), chunks, usage, fail: false }
+    }
+
+    #[test]
+    fn timings_come_from_chunk_arrivals() {
+        let mut phases = Vec::new();
+        let result = run_with(&backend(5, Usage::default()), &request(), &mut |p| phases.push(p.phase)).unwrap();
+
+        let ttft = result.time_to_first_token_ms.unwrap();
+        assert!(ttft >= 20.0, "{}", ttft);
+        assert!(result.total_latency_ms >= ttft + 4.0 * 20.0);
+        assert_eq!(result.output_chunks, 5);
+        assert_eq!(result.output_chars, 10);
+        assert_eq!(result.inter_token_latency.count, 4);
+        assert!(result.inter_token_latency.min_ms >= 20.0, "{}", result.inter_token_latency.min_ms);
+        assert!(result.inter_token_latency.p50_ms <= result.inter_token_latency.max_ms);
+        assert!(matches!(phases.first(), Some(BenchmarkPhase::Started)));
+        assert!(matches!(phases.get(1), Some(BenchmarkPhase::FirstToken)));
+        assert!(matches!(phases.last(), Some(BenchmarkPhase::Finished)));
+
+        // No token counts reported: chunks stand in for tokens, and the
+        // first one is left out of the generation rate
+        assert_eq!(result.completion_tokens, None);
+        assert!(result.completion_tokens_estimated);
+        let window = (result.total_latency_ms - ttft) / 1000.0;
+        let rate = result.generation_tokens_per_second.unwrap();
+        assert!((rate - 4.0 / window).abs() < 1e-6, "{} vs {}", rate, 4.0 / window);
+        assert_eq!(result.prompt_tokens_per_second, None);
+    }
+
+    #[test]
+    fn reported_usage_takes_precedence() {
+        let usage = Usage {
+            prompt_tokens: Some(8),
+            completion_tokens: Some(12),
+            load_ms: None,
+            prompt_eval_ms: None,
+            eval_ms: Some(400.0),
+        };
+        let result = run_with(&backend(3, usage), &request(), &mut |_| {}).unwrap();
+
+        assert_eq!(result.completion_tokens, Some(12));
+        assert!(!result.completion_tokens_estimated);
+        assert!((result.generation_tokens_per_second.unwrap() - 30.0).abs() < 1e-9);
+        // No prompt timing from the server: the time to first token is used
+        let ttft = result.time_to_first_token_ms.unwrap() / 1000.0;
+        assert!((result.prompt_tokens_per_second.unwrap() - 8.0 / ttft).abs() < 1e-6);
+    }
+
+    #[test]
+    fn single_chunk_has_no_fallback_rate() {
+        let result = run_with(&backend(1, Usage::default()), &request(), &mut |_| {}).unwrap();
+        assert_eq!(result.inter_token_latency.count, 0);
+        assert_eq!(result.generation_tokens_per_second, None);
+        assert!(result.completion_tokens_estimated);
+    }
+
+    #[test]
+    fn stream_errors_are_reported_as_failed() {
+        let failing = FakeBackend { fail: true, ..backend(2, Usage::default()) };
+        let mut last = None;
+        let err = run_with(&failing, &request(), &mut |p| last = Some(p)).err().unwrap();
+        assert!(matches!(err.code, ErrorCode::BackendError));
+        let last = last.unwrap();
+        assert!(matches!(last.phase, BenchmarkPhase::Failed));
+        assert_eq!(last.chunks, 2);
+        assert_eq!(last.error.as_deref(), Some("connection reset mid-stream"));
+    }
+}
@@ -0,0 +1,97 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Stable, machine-readable error codes; the frontend switches on these, so
+// existing variants must not be renamed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    NotFound,
+    PermissionDenied,
+    InvalidInput,
+    Corrupt,
+    Io,
+    Database,
+    BackendUnavailable,
+    BackendError,
+    Timeout,
+    Cancelled,
+    Internal,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AppError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<String>,
+}
+
+impl AppError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), details: None }
+    }
+
+    pub fn with_details(mut self, details: impl Into<String>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::NotFound, message)
+    }
+
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::InvalidInput, message)
+    }
+
+    pub fn corrupt(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Corrupt, message)
+    }
+
+    pub fn backend(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::BackendError, message)
+    }
+
+    pub fn cancelled(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Cancelled, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(ErrorCode::Internal, message)
+    }
+
+    // `context` describes the failed operation, e.g. "Failed to read file"
+    pub fn io(context: &str, e: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+
+        let code = match e.kind() {
+            ErrorKind::NotFound => ErrorCode::NotFound,
+            ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            ErrorKind::TimedOut | ErrorKind::WouldBlock => ErrorCode::Timeout,
+            ErrorKind::InvalidInput => ErrorCode::InvalidInput,
+            ErrorKind::InvalidData | ErrorKind::UnexpectedEof => ErrorCode::Corrupt,
+            _ => ErrorCode::Io,
+        };
+        Self::new(code, format!("{}: {}", context, e))
+    }
+
+    pub fn database(context: &str, e: rusqlite::Error) -> Self {
+        use rusqlite::ErrorCode as SqliteCode;
+
+        let code = match e.sqlite_error_code() {
+            Some(SqliteCode::DatabaseCorrupt | SqliteCode::NotADatabase) => ErrorCode::Corrupt,
+            Some(SqliteCode::DatabaseBusy | SqliteCode::DatabaseLocked) => ErrorCode::Timeout,
+            Some(SqliteCode::PermissionDenied | SqliteCode::ReadOnly) => ErrorCode::PermissionDenied,
+            _ => ErrorCode::Database,
+        };
+        Self::new(code, format!("{}: {}", context, e))
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
@@ -0,0 +1,36 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Serialize, Deserialize)]
+pub struct FileInfo {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    pub is_directory: bool,
+    pub modified: u64,
+}
+
+pub fn list(path: &Path) -> Result<Vec<FileInfo>, AppError> {
+    let entries = std::fs::read_dir(path)
+        .map_err(|e| AppError::io("Failed to read directory", e))?;
+
+    let mut files = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|e| AppError::io("Failed to read entry", e))?;
+        let metadata = entry.metadata()
+            .map_err(|e| AppError::io("Failed to get metadata", e))?;
+
+        files.push(FileInfo {
+            name: entry.file_name().to_string_lossy().to_string(),
+            path: entry.path().to_string_lossy().to_string(),
+            size: metadata.len(),
+            is_directory: metadata.is_dir(),
+            modified: metadata.modified()
+                .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs())
+                .unwrap_or(0),
+        });
+    }
+
+    Ok(files)
+}
@@ -0,0 +1,374 @@
+use crate::error::{AppError, ErrorCode};
+use crate::models;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// Backend-only settings live here; the webview may read but never write it
+const CONFIG_DIR: &str = "config";
+const CONFIG_FILE: &str = "allowed_roots.json";
+const EXPORT_DIR: &str = "exports";
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RootKind {
+    AppData,
+    Export,
+    Models,
+    Custom,
+}
+
+impl RootKind {
+    // The app data dir holds the results database and model dirs hold the
+    // models; the webview may only read those
+    fn writable(self) -> bool {
+        matches!(self, RootKind::Export | RootKind::Custom)
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct AllowedRoot {
+    pub path: String,
+    pub kind: RootKind,
+    pub writable: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct AllowedRoots {
+    pub roots: Vec<AllowedRoot>,
+    pub config_file: String,
+    pub config_error: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct ScopeConfig {
+    #[serde(default)]
+    roots: Vec<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum ScopeError {
+    Denied { path: PathBuf, reason: &'static str },
+    InvalidPath(String),
+    Io(AppError),
+}
+
+impl fmt::Display for ScopeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScopeError::Denied { path, reason } => write!(f, "Access denied: {} {}", path.display(), reason),
+            ScopeError::InvalidPath(message) => write!(f, "Invalid path: {}", message),
+            ScopeError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<ScopeError> for AppError {
+    fn from(e: ScopeError) -> Self {
+        match e {
+            ScopeError::Denied { ref path, .. } => {
+                let path = path.display().to_string();
+                AppError::new(ErrorCode::PermissionDenied, e.to_string()).with_details(path)
+            }
+            ScopeError::InvalidPath(_) => AppError::invalid_input(e.to_string()),
+            ScopeError::Io(e) => e,
+        }
+    }
+}
+
+// Filesystem access granted to the webview. Roots are canonicalized once at
+// startup; every request path is canonicalized too, so `..` segments and
+// symlinks are resolved before the prefix check.
+pub struct FsScope {
+    roots: Vec<(PathBuf, RootKind)>,
+    config_dir: PathBuf,
+    config_file: PathBuf,
+    config_error: Option<String>,
+}
+
+impl FsScope {
+    pub fn load(data_dir: &Path) -> Self {
+        let export_dir = data_dir.join(EXPORT_DIR);
+        let _ = std::fs::create_dir_all(&export_dir);
+        // Created up front so a file written under its name cannot take its place
+        let config_dir = config_dir(data_dir);
+        let _ = std::fs::create_dir_all(&config_dir);
+        let config_dir = config_dir.canonicalize().unwrap_or(config_dir);
+
+        let mut candidates = vec![
+            (data_dir.to_path_buf(), RootKind::AppData),
+            (export_dir, RootKind::Export),
+        ];
+        candidates.extend(models::default_dirs().into_iter().map(|dir| (dir.path, RootKind::Models)));
+
+        let config_file = config_dir.join(CONFIG_FILE);
+        let config_error = match read_config(&config_file) {
+            Ok(config) => {
+                candidates.extend(config.roots.into_iter().map(|dir| (dir, RootKind::Custom)));
+                None
+            }
+            Err(e) => Some(e),
+        };
+
+        // Roots that do not exist yet cannot be canonicalized and are skipped
+        let mut roots: Vec<(PathBuf, RootKind)> = Vec::new();
+        for (dir, kind) in candidates {
+            if let Ok(dir) = dir.canonicalize() {
+                if !roots.iter().any(|(root, _)| *root == dir) {
+                    roots.push((dir, kind));
+                }
+            }
+        }
+
+        Self { roots, config_dir, config_file, config_error }
+    }
+
+    // Folders the user added in allowed_roots.json
+    pub fn custom_roots(&self) -> Vec<PathBuf> {
+        self.roots.iter()
+            .filter(|(_, kind)| *kind == RootKind::Custom)
+            .map(|(path, _)| path.clone())
+            .collect()
+    }
+
+    pub fn allowed_roots(&self) -> AllowedRoots {
+        AllowedRoots {
+            roots: self.roots.iter()
+                .map(|(path, kind)| AllowedRoot {
+                    path: path.display().to_string(),
+                    kind: *kind,
+                    writable: kind.writable(),
+                })
+                .collect(),
+            config_file: self.config_file.display().to_string(),
+            config_error: self.config_error.clone(),
+        }
+    }
+
+    // Resolves an existing file or directory inside the scope.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, ScopeError> {
+        let path = absolute(path)?;
+        let resolved = path.canonicalize()
+            .map_err(|e| ScopeError::Io(AppError::io(&format!("Failed to resolve {}", path.display()), e)))?;
+        self.check(resolved)
+    }
+
+    // Resolves a file that may not exist yet. The parent directory must
+    // exist and be inside the scope; an existing target is resolved in full
+    // so a symlink cannot redirect the write.
+    pub fn resolve_for_write(&self, path: &str) -> Result<PathBuf, ScopeError> {
+        let path = absolute(path)?;
+        if path.exists() {
+            return self.resolve(&path.to_string_lossy()).and_then(|resolved| self.check_writable(resolved));
+        }
+
+        let file_name = path.file_name()
+            .ok_or_else(|| ScopeError::InvalidPath(format!("{} has no file name", path.display())))?;
+        let parent = path.parent()
+            .ok_or_else(|| ScopeError::InvalidPath(format!("{} has no parent directory", path.display())))?;
+        let parent = parent.canonicalize()
+            .map_err(|e| ScopeError::Io(AppError::io(&format!("Failed to resolve {}", parent.display()), e)))?;
+        self.check(parent.join(file_name)).and_then(|resolved| self.check_writable(resolved))
+    }
+
+    // Whether the webview could replace a resolved path, e.g. a binary the
+    // backend is about to run
+    pub fn is_writable(&self, resolved: &Path) -> bool {
+        self.check(resolved.to_path_buf())
+            .and_then(|resolved| self.check_writable(resolved))
+            .is_ok()
+    }
+
+    // The webview must not be able to widen its own scope. Compared without
+    // case, since macOS and Windows filesystems usually ignore it. Otherwise
+    // the innermost root decides, so exports stay writable inside the app
+    // data dir and the data dir stays read-only inside a custom root.
+    fn check_writable(&self, resolved: PathBuf) -> Result<PathBuf, ScopeError> {
+        let lower = |path: &Path| PathBuf::from(path.to_string_lossy().to_lowercase());
+        if lower(&resolved).starts_with(lower(&self.config_dir)) {
+            return Err(ScopeError::Denied { path: resolved, reason: "is in the configuration directory" });
+        }
+        let innermost = self.roots.iter()
+            .filter(|(root, _)| resolved.starts_with(root))
+            .max_by_key(|(root, _)| root.components().count());
+        match innermost {
+            Some((_, kind)) if kind.writable() => Ok(resolved),
+            Some(_) => Err(ScopeError::Denied { path: resolved, reason: "is in a read-only folder" }),
+            None => Err(ScopeError::Denied { path: resolved, reason: "is outside the allowed roots" }),
+        }
+    }
+
+    fn check(&self, resolved: PathBuf) -> Result<PathBuf, ScopeError> {
+        if self.roots.iter().any(|(root, _)| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(ScopeError::Denied { path: resolved, reason: "is outside the allowed roots" })
+        }
+    }
+}
+
+pub fn config_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(CONFIG_DIR)
+}
+
+fn absolute(path: &str) -> Result<PathBuf, ScopeError> {
+    let path = PathBuf::from(path);
+    if !path.is_absolute() {
+        return Err(ScopeError::InvalidPath(format!("{} is not an absolute path", path.display())));
+    }
+    Ok(path)
+}
+
+fn read_config(config_file: &Path) -> Result<ScopeConfig, String> {
+    if !config_file.exists() {
+        return Ok(ScopeConfig::default());
+    }
+
+    let content = std::fs::read_to_string(config_file)
+        .map_err(|e| format!("Failed to read {}: {}", config_file.display(), e))?;
+    let config: ScopeConfig = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", config_file.display(), e))?;
+
+    if let Some(relative) = config.roots.iter().find(|root| !root.is_absolute()) {
+        return Err(format!("Allowed root {} is not an absolute path", relative.display()));
+    }
+    Ok(config)
+}
+
+// A scope over a temporary app data dir, with a sibling folder outside it
+#[cfg(test)]
+pub mod testing {
+    use super::FsScope;
+    use std::path::PathBuf;
+
+    pub struct Fixture {
+        _dir: tempfile::TempDir,
+        pub data: PathBuf,
+        pub outside: PathBuf,
+        pub scope: FsScope,
+    }
+
+    pub fn fixture() -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        let data = base.join("data");
+        let outside = base.join("outside");
+        std::fs::create_dir_all(data.join("sub")).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), "secret").unwrap();
+        std::fs::write(data.join("notes.txt"), "notes").unwrap();
+        let scope = FsScope::load(&data);
+        Fixture { _dir: dir, data, outside, scope }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::fixture;
+    use super::*;
+
+    fn is_denied<T>(result: Result<T, ScopeError>) -> bool {
+        matches!(result, Err(ScopeError::Denied { .. }))
+    }
+
+    fn path(path: &Path) -> String {
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn resolves_paths_inside_roots() {
+        let f = fixture();
+        let export = f.data.join(EXPORT_DIR).join("x.json");
+        assert_eq!(f.scope.resolve(&path(&f.data.join("notes.txt"))).unwrap(), f.data.join("notes.txt"));
+        assert_eq!(f.scope.resolve_for_write(&path(&export)).unwrap(), export);
+    }
+
+    #[test]
+    fn app_data_is_read_only_outside_exports() {
+        let f = fixture();
+        for name in ["results.db", "results.db-wal", "results.db-shm", "storage.json", "sub/new.txt"] {
+            assert!(is_denied(f.scope.resolve_for_write(&path(&f.data.join(name)))), "{}", name);
+        }
+        assert!(f.scope.resolve(&path(&f.data.join("notes.txt"))).is_ok());
+        assert!(f.scope.is_writable(&f.data.join(EXPORT_DIR).join("x.json")));
+        assert!(!f.scope.is_writable(&f.data.join("notes.txt")));
+    }
+
+    #[test]
+    fn custom_root_containing_app_data_keeps_it_read_only() {
+        let f = fixture();
+        let base = f.data.parent().unwrap().to_path_buf();
+        std::fs::write(
+            config_dir(&f.data).join(CONFIG_FILE),
+            serde_json::json!({ "roots": [base] }).to_string(),
+        )
+        .unwrap();
+        let scope = FsScope::load(&f.data);
+        assert!(scope.resolve_for_write(&path(&f.outside.join("new.txt"))).is_ok());
+        assert!(is_denied(scope.resolve_for_write(&path(&f.data.join("results.db")))));
+        assert!(scope.resolve_for_write(&path(&f.data.join(EXPORT_DIR).join("x.json"))).is_ok());
+    }
+
+    #[test]
+    fn dot_dot_cannot_escape() {
+        let f = fixture();
+        let escape = f.data.join("sub").join("..").join("..").join("outside").join("secret.txt");
+        assert!(is_denied(f.scope.resolve(&path(&escape))));
+        let escape = f.data.join("..").join("outside").join("new.txt");
+        assert!(is_denied(f.scope.resolve_for_write(&path(&escape))));
+    }
+
+    #[test]
+    fn relative_paths_are_rejected() {
+        let f = fixture();
+        assert!(matches!(f.scope.resolve("notes.txt"), Err(ScopeError::InvalidPath(_))));
+        assert!(matches!(f.scope.resolve_for_write("../new.txt"), Err(ScopeError::InvalidPath(_))));
+    }
+
+    #[test]
+    fn missing_parent_is_not_found() {
+        let f = fixture();
+        let err = f.scope.resolve_for_write(&path(&f.data.join("missing/new.txt"))).err().unwrap();
+        assert_eq!(AppError::from(err).code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn config_directory_is_read_only() {
+        let f = fixture();
+        let config = config_dir(&f.data);
+        assert!(is_denied(f.scope.resolve_for_write(&path(&config.join(CONFIG_FILE)))));
+        assert!(is_denied(f.scope.resolve_for_write(&path(&config.join("ALLOWED_ROOTS.json")))));
+        assert!(is_denied(f.scope.resolve_for_write(&path(&config))));
+        // The old location next to the database is no longer read
+        std::fs::write(f.data.join(CONFIG_FILE), serde_json::json!({ "roots": [f.outside] }).to_string()).unwrap();
+        assert!(FsScope::load(&f.data).custom_roots().is_empty());
+    }
+
+    #[test]
+    fn custom_roots_come_from_config() {
+        let f = fixture();
+        std::fs::write(
+            config_dir(&f.data).join(CONFIG_FILE),
+            serde_json::json!({ "roots": [f.outside] }).to_string(),
+        )
+        .unwrap();
+        let scope = FsScope::load(&f.data);
+        assert_eq!(scope.custom_roots(), std::slice::from_ref(&f.outside));
+        assert!(scope.resolve(&path(&f.outside.join("secret.txt"))).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_cannot_escape() {
+        use std::os::unix::fs::symlink;
+
+        let f = fixture();
+        symlink(f.outside.join("secret.txt"), f.data.join("secret-link")).unwrap();
+        symlink(&f.outside, f.data.join("outside-link")).unwrap();
+
+        assert!(is_denied(f.scope.resolve(&path(&f.data.join("secret-link")))));
+        assert!(is_denied(f.scope.resolve(&path(&f.data.join("outside-link/secret.txt")))));
+        assert!(is_denied(f.scope.resolve_for_write(&path(&f.data.join("secret-link")))));
+        assert!(is_denied(f.scope.resolve_for_write(&path(&f.data.join("outside-link/new.txt")))));
+    }
+}
@@ -0,0 +1,559 @@
+use crate::error::AppError;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+// Sanity limits so a damaged header fails fast instead of allocating wildly
+const MAX_STRING_BYTES: u64 = 16 * 1024 * 1024;
+const MAX_KV_COUNT: u64 = 1_000_000;
+const MAX_TENSOR_COUNT: u64 = 10_000_000;
+const MAX_DIMS: u32 = 8;
+// Arrays of arrays recurse; real files nest at most once or twice
+const MAX_ARRAY_DEPTH: u32 = 8;
+// Longer arrays (token lists, merges) are counted but not kept
+const MAX_KEPT_ARRAY: u64 = 64;
+
+#[derive(Serialize, Clone)]
+pub struct GgufInfo {
+    pub path: String,
+    pub file_size: u64,
+    pub version: u32,
+    pub tensor_count: u64,
+    pub architecture: Option<String>,
+    pub name: Option<String>,
+    pub file_type: Option<String>,
+    pub parameter_count: u64,
+    pub context_length: Option<u64>,
+    pub embedding_length: Option<u64>,
+    pub block_count: Option<u64>,
+    pub head_count: Option<u64>,
+    pub head_count_kv: Option<u64>,
+    pub feed_forward_length: Option<u64>,
+    pub tokenizer_model: Option<String>,
+    pub vocab_size: Option<u64>,
+    pub chat_template: Option<String>,
+    // Tensor class (attention, feed_forward, ...) -> tensor type -> count
+    pub tensor_types: BTreeMap<String, BTreeMap<String, u64>>,
+    // Remaining scalar and short array metadata, keyed as in the file
+    pub metadata: BTreeMap<String, serde_json::Value>,
+}
+
+enum Value {
+    Unsigned(u64),
+    Signed(i64),
+    Float(f64),
+    Bool(bool),
+    String(String),
+    Array { len: u64, items: Vec<Value> },
+}
+
+impl Value {
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            Value::Unsigned(v) => Some(*v),
+            Value::Signed(v) => u64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        match self {
+            Value::Unsigned(v) => (*v).into(),
+            Value::Signed(v) => (*v).into(),
+            Value::Float(v) => (*v).into(),
+            Value::Bool(v) => (*v).into(),
+            Value::String(v) => v.clone().into(),
+            Value::Array { len, items } if *len == items.len() as u64 => {
+                items.iter().map(Value::to_json).collect::<Vec<_>>().into()
+            }
+            Value::Array { len, .. } => format!("[{} items]", len).into(),
+        }
+    }
+}
+
+struct TensorInfo {
+    name: String,
+    elements: u64,
+    ggml_type: u32,
+}
+
+pub fn inspect(path: &Path) -> Result<GgufInfo, AppError> {
+    let file = File::open(path)
+        .map_err(|e| AppError::io("Failed to open model file", e))?;
+    let file_size = file.metadata()
+        .map_err(|e| AppError::io("Failed to get metadata", e))?
+        .len();
+    let mut reader = GgufReader { inner: BufReader::new(file) };
+
+    let mut magic = [0u8; 4];
+    reader.fill(&mut magic)?;
+    if &magic != GGUF_MAGIC {
+        return Err(AppError::invalid_input(format!("{} is not a GGUF file", path.display())));
+    }
+    let version = reader.u32()?;
+    if !(2..=3).contains(&version) {
+        return Err(AppError::invalid_input(format!("Unsupported GGUF version {}", version)));
+    }
+
+    let tensor_count = reader.count(MAX_TENSOR_COUNT, "tensor count")?;
+    let kv_count = reader.count(MAX_KV_COUNT, "metadata count")?;
+
+    let mut kv: BTreeMap<String, Value> = BTreeMap::new();
+    for _ in 0..kv_count {
+        let key = reader.string()?;
+        let value_type = reader.u32()?;
+        let value = reader.value(value_type, 0)?;
+        kv.insert(key, value);
+    }
+
+    let mut tensors = Vec::new();
+    for _ in 0..tensor_count {
+        let name = reader.string()?;
+        let n_dims = reader.u32()?;
+        if n_dims > MAX_DIMS {
+            return Err(AppError::corrupt(format!("Tensor {} has {} dimensions", name, n_dims)));
+        }
+        let mut elements: u64 = 1;
+        for _ in 0..n_dims {
+            elements = elements.saturating_mul(reader.u64()?);
+        }
+        let ggml_type = reader.u32()?;
+        let _offset = reader.u64()?;
+        tensors.push(TensorInfo { name, elements, ggml_type });
+    }
+
+    Ok(summarize(path, file_size, version, kv, &tensors))
+}
+
+fn summarize(
+    path: &Path,
+    file_size: u64,
+    version: u32,
+    mut kv: BTreeMap<String, Value>,
+    tensors: &[TensorInfo],
+) -> GgufInfo {
+    let architecture = kv.remove("general.architecture").and_then(|v| v.as_str().map(str::to_string));
+    let arch = architecture.clone().unwrap_or_default();
+    let mut arch_u64 = |suffix: &str| kv.remove(&format!("{}.{}", arch, suffix)).and_then(|v| v.as_u64());
+
+    let context_length = arch_u64("context_length");
+    let embedding_length = arch_u64("embedding_length");
+    let block_count = arch_u64("block_count");
+    let head_count = arch_u64("attention.head_count");
+    let head_count_kv = arch_u64("attention.head_count_kv");
+    let feed_forward_length = arch_u64("feed_forward_length");
+
+    let vocab_size = match kv.remove("tokenizer.ggml.tokens") {
+        Some(Value::Array { len, .. }) => Some(len),
+        _ => None,
+    };
+
+    let mut tensor_types: BTreeMap<String, BTreeMap<String, u64>> = BTreeMap::new();
+    for tensor in tensors {
+        *tensor_types
+            .entry(tensor_class(&tensor.name).to_string())
+            .or_default()
+            .entry(ggml_type_name(tensor.ggml_type))
+            .or_default() += 1;
+    }
+
+    GgufInfo {
+        path: path.display().to_string(),
+        file_size,
+        version,
+        tensor_count: tensors.len() as u64,
+        architecture,
+        name: kv.remove("general.name").and_then(|v| v.as_str().map(str::to_string)),
+        file_type: kv.remove("general.file_type").and_then(|v| v.as_u64()).map(file_type_name),
+        parameter_count: tensors.iter().map(|t| t.elements).sum(),
+        context_length,
+        embedding_length,
+        block_count,
+        head_count,
+        head_count_kv,
+        feed_forward_length,
+        tokenizer_model: kv.remove("tokenizer.ggml.model").and_then(|v| v.as_str().map(str::to_string)),
+        vocab_size,
+        chat_template: kv.remove("tokenizer.chat_template").and_then(|v| v.as_str().map(str::to_string)),
+        tensor_types,
+        metadata: kv.iter().map(|(k, v)| (k.clone(), v.to_json())).collect(),
+    }
+}
+
+// Groups tensors by role using llama.cpp's naming, e.g. `blk.3.attn_q.weight`
+fn tensor_class(name: &str) -> &'static str {
+    let base = name.strip_prefix("blk.")
+        .and_then(|rest| rest.split_once('.'))
+        .map(|(_, rest)| rest)
+        .unwrap_or(name);
+
+    if base.contains("norm") {
+        "norm"
+    } else if base.starts_with("attn") {
+        "attention"
+    } else if base.starts_with("ffn") {
+        "feed_forward"
+    } else if base.starts_with("token_embd") {
+        "embedding"
+    } else if base.starts_with("output") {
+        "output"
+    } else {
+        "other"
+    }
+}
+
+fn ggml_type_name(ggml_type: u32) -> String {
+    let name = match ggml_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        6 => "Q5_0",
+        7 => "Q5_1",
+        8 => "Q8_0",
+        9 => "Q8_1",
+        10 => "Q2_K",
+        11 => "Q3_K",
+        12 => "Q4_K",
+        13 => "Q5_K",
+        14 => "Q6_K",
+        15 => "Q8_K",
+        16 => "IQ2_XXS",
+        17 => "IQ2_XS",
+        18 => "IQ3_XXS",
+        19 => "IQ1_S",
+        20 => "IQ4_NL",
+        21 => "IQ3_S",
+        22 => "IQ2_S",
+        23 => "IQ4_XS",
+        24 => "I8",
+        25 => "I16",
+        26 => "I32",
+        27 => "I64",
+        28 => "F64",
+        29 => "IQ1_M",
+        30 => "BF16",
+        34 => "TQ1_0",
+        35 => "TQ2_0",
+        other => return format!("UNKNOWN_{}", other),
+    };
+    name.to_string()
+}
+
+// `general.file_type`, the quantization preset the whole file was made with
+fn file_type_name(file_type: u64) -> String {
+    let name = match file_type {
+        0 => "F32",
+        1 => "F16",
+        2 => "Q4_0",
+        3 => "Q4_1",
+        7 => "Q8_0",
+        8 => "Q5_0",
+        9 => "Q5_1",
+        10 => "Q2_K",
+        11 => "Q3_K_S",
+        12 => "Q3_K_M",
+        13 => "Q3_K_L",
+        14 => "Q4_K_S",
+        15 => "Q4_K_M",
+        16 => "Q5_K_S",
+        17 => "Q5_K_M",
+        18 => "Q6_K",
+        19 => "IQ2_XXS",
+        20 => "IQ2_XS",
+        21 => "Q2_K_S",
+        22 => "IQ3_XS",
+        23 => "IQ3_XXS",
+        24 => "IQ1_S",
+        25 => "IQ4_NL",
+        26 => "IQ3_S",
+        27 => "IQ3_M",
+        28 => "IQ2_S",
+        29 => "IQ2_M",
+        30 => "IQ4_XS",
+        31 => "IQ1_M",
+        32 => "BF16",
+        36 => "TQ1_0",
+        37 => "TQ2_0",
+        other => return format!("UNKNOWN_{}", other),
+    };
+    name.to_string()
+}
+
+// Little-endian primitive reader over the GGUF header
+struct GgufReader<R> {
+    inner: R,
+}
+
+impl<R: Read> GgufReader<R> {
+    fn fill(&mut self, buf: &mut [u8]) -> Result<(), AppError> {
+        self.inner.read_exact(buf)
+            .map_err(|e| AppError::io("Failed to read GGUF header", e))
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], AppError> {
+        let mut buf = [0u8; N];
+        self.fill(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn u32(&mut self) -> Result<u32, AppError> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, AppError> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    fn count(&mut self, max: u64, what: &str) -> Result<u64, AppError> {
+        let count = self.u64()?;
+        if count > max {
+            return Err(AppError::corrupt(format!("Implausible GGUF {}: {}", what, count)));
+        }
+        Ok(count)
+    }
+
+    fn string(&mut self) -> Result<String, AppError> {
+        let len = self.count(MAX_STRING_BYTES, "string length")?;
+        let mut buf = vec![0u8; len as usize];
+        self.fill(&mut buf)?;
+        Ok(String::from_utf8_lossy(&buf).into_owned())
+    }
+
+    fn skip_string(&mut self) -> Result<(), AppError> {
+        let len = self.count(MAX_STRING_BYTES, "string length")?;
+        let skipped = io::copy(&mut (&mut self.inner).take(len), &mut io::sink())
+            .map_err(|e| AppError::io("Failed to read GGUF header", e))?;
+        if skipped != len {
+            return Err(AppError::corrupt("GGUF header ends inside a string"));
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, value_type: u32, depth: u32) -> Result<Value, AppError> {
+        let value = match value_type {
+            0 => Value::Unsigned(u8::from_le_bytes(self.bytes()?) as u64),
+            1 => Value::Signed(i8::from_le_bytes(self.bytes()?) as i64),
+            2 => Value::Unsigned(u16::from_le_bytes(self.bytes()?) as u64),
+            3 => Value::Signed(i16::from_le_bytes(self.bytes()?) as i64),
+            4 => Value::Unsigned(self.u32()? as u64),
+            5 => Value::Signed(i32::from_le_bytes(self.bytes()?) as i64),
+            6 => Value::Float(f32::from_le_bytes(self.bytes()?) as f64),
+            7 => Value::Bool(self.bytes::<1>()?[0] != 0),
+            8 => Value::String(self.string()?),
+            9 => {
+                let (item_type, len) = self.array_header(depth)?;
+                let mut items = Vec::new();
+                for index in 0..len {
+                    if index >= MAX_KEPT_ARRAY {
+                        self.skip_value(item_type, depth + 1)?;
+                    } else {
+                        items.push(self.value(item_type, depth + 1)?);
+                    }
+                }
+                Value::Array { len, items }
+            }
+            10 => Value::Unsigned(self.u64()?),
+            11 => Value::Signed(i64::from_le_bytes(self.bytes()?)),
+            12 => Value::Float(f64::from_le_bytes(self.bytes()?)),
+            other => return Err(AppError::corrupt(format!("Unknown GGUF value type {}", other))),
+        };
+        Ok(value)
+    }
+
+    fn skip_value(&mut self, value_type: u32, depth: u32) -> Result<(), AppError> {
+        match value_type {
+            8 => self.skip_string(),
+            9 => {
+                let (item_type, len) = self.array_header(depth)?;
+                for _ in 0..len {
+                    self.skip_value(item_type, depth + 1)?;
+                }
+                Ok(())
+            }
+            _ => self.value(value_type, depth).map(|_| ()),
+        }
+    }
+
+    fn array_header(&mut self, depth: u32) -> Result<(u32, u64), AppError> {
+        if depth >= MAX_ARRAY_DEPTH {
+            return Err(AppError::corrupt(format!("GGUF arrays nested deeper than {}", MAX_ARRAY_DEPTH)));
+        }
+        Ok((self.u32()?, self.u64()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+
+    fn string(s: &str) -> Vec<u8> {
+        let mut out = (s.len() as u64).to_le_bytes().to_vec();
+        out.extend(s.as_bytes());
+        out
+    }
+
+    fn kv(key: &str, value_type: u32, value: Vec<u8>) -> Vec<u8> {
+        let mut out = string(key);
+        out.extend(value_type.to_le_bytes());
+        out.extend(value);
+        out
+    }
+
+    fn array(item_type: u32, items: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = item_type.to_le_bytes().to_vec();
+        out.extend((items.len() as u64).to_le_bytes());
+        out.extend(items.concat());
+        out
+    }
+
+    fn header(version: u32, kvs: &[Vec<u8>], tensors: &[(&str, &[u64], u32)]) -> Vec<u8> {
+        let mut out = b"GGUF".to_vec();
+        out.extend(version.to_le_bytes());
+        out.extend((tensors.len() as u64).to_le_bytes());
+        out.extend((kvs.len() as u64).to_le_bytes());
+        out.extend(kvs.concat());
+        for (name, dims, ggml_type) in tensors {
+            out.extend(string(name));
+            out.extend((dims.len() as u32).to_le_bytes());
+            for dim in *dims {
+                out.extend(dim.to_le_bytes());
+            }
+            out.extend(ggml_type.to_le_bytes());
+            out.extend(0u64.to_le_bytes());
+        }
+        out
+    }
+
+    fn tiny_model() -> Vec<u8> {
+        let tokens: Vec<Vec<u8>> = (0..100).map(|i| string(&format!("t{}", i))).collect();
+        let token_types: Vec<Vec<u8>> = (0..3).map(|i: i32| i.to_le_bytes().to_vec()).collect();
+        header(
+            3,
+            &[
+                kv("general.architecture", 8, string("llama")),
+                kv("general.name", 8, string("Tiny")),
+                kv("general.file_type", 4, 15u32.to_le_bytes().to_vec()),
+                kv("llama.context_length", 4, 4096u32.to_le_bytes().to_vec()),
+                kv("llama.block_count", 4, 2u32.to_le_bytes().to_vec()),
+                kv("llama.rope.freq_base", 6, 10000f32.to_le_bytes().to_vec()),
+                kv("tokenizer.ggml.model", 8, string("gpt2")),
+                kv("tokenizer.ggml.tokens", 9, array(8, &tokens)),
+                kv("tokenizer.ggml.token_type", 9, array(5, &token_types)),
+            ],
+            &[
+                ("token_embd.weight", &[64, 100], 12),
+                ("output_norm.weight", &[64], 0),
+                ("blk.0.attn_q.weight", &[64, 64], 12),
+                ("blk.0.attn_k.weight", &[64, 64], 14),
+                ("blk.0.attn_norm.weight", &[64], 0),
+                ("blk.0.ffn_down.weight", &[128, 64], 14),
+                ("blk.1.attn_q.weight", &[64, 64], 12),
+            ],
+        )
+    }
+
+    fn inspect_bytes(bytes: &[u8]) -> Result<GgufInfo, AppError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.gguf");
+        std::fs::write(&path, bytes).unwrap();
+        inspect(&path)
+    }
+
+    #[test]
+    fn reads_architecture_and_context_length() {
+        let info = inspect_bytes(&tiny_model()).unwrap();
+        assert_eq!(info.version, 3);
+        assert_eq!(info.architecture.as_deref(), Some("llama"));
+        assert_eq!(info.name.as_deref(), Some("Tiny"));
+        assert_eq!(info.file_type.as_deref(), Some("Q4_K_M"));
+        assert_eq!(info.context_length, Some(4096));
+        assert_eq!(info.block_count, Some(2));
+        assert_eq!(info.tokenizer_model.as_deref(), Some("gpt2"));
+        assert_eq!(info.metadata["llama.rope.freq_base"], 10000.0);
+    }
+
+    #[test]
+    fn counts_tensor_types_per_class() {
+        let info = inspect_bytes(&tiny_model()).unwrap();
+        assert_eq!(info.tensor_count, 7);
+        assert_eq!(info.tensor_types["attention"]["Q4_K"], 2);
+        assert_eq!(info.tensor_types["attention"]["Q6_K"], 1);
+        assert_eq!(info.tensor_types["norm"]["F32"], 2);
+        assert_eq!(info.tensor_types["feed_forward"]["Q6_K"], 1);
+        assert_eq!(info.tensor_types["embedding"]["Q4_K"], 1);
+    }
+
+    #[test]
+    fn sums_parameter_count() {
+        let info = inspect_bytes(&tiny_model()).unwrap();
+        assert_eq!(info.parameter_count, 6400 + 64 + 4096 * 3 + 64 + 8192);
+    }
+
+    #[test]
+    fn long_arrays_are_counted_not_kept() {
+        let info = inspect_bytes(&tiny_model()).unwrap();
+        assert_eq!(info.vocab_size, Some(100));
+        assert_eq!(info.metadata["tokenizer.ggml.token_type"], serde_json::json!([0, 1, 2]));
+
+        let items: Vec<Vec<u8>> = (0..MAX_KEPT_ARRAY + 1).map(|i| (i as u32).to_le_bytes().to_vec()).collect();
+        let bytes = header(3, &[kv("general.ids", 9, array(4, &items))], &[]);
+        let info = inspect_bytes(&bytes).unwrap();
+        assert_eq!(info.metadata["general.ids"], format!("[{} items]", MAX_KEPT_ARRAY + 1));
+    }
+
+    #[test]
+    fn rejects_bad_magic_and_version() {
+        let mut bytes = tiny_model();
+        bytes[..4].copy_from_slice(b"GGML");
+        assert_eq!(inspect_bytes(&bytes).err().unwrap().code, ErrorCode::InvalidInput);
+
+        let bytes = header(1, &[], &[]);
+        let err = inspect_bytes(&bytes).err().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert!(err.message.contains("version 1"));
+    }
+
+    #[test]
+    fn truncated_file_is_corrupt() {
+        let bytes = tiny_model();
+        for len in [6, 30, bytes.len() / 2, bytes.len() - 1] {
+            assert_eq!(inspect_bytes(&bytes[..len]).err().unwrap().code, ErrorCode::Corrupt, "length {}", len);
+        }
+    }
+
+    #[test]
+    fn deeply_nested_arrays_are_corrupt() {
+        // Each level is an array of one array; the innermost never arrives
+        let nested: Vec<u8> = (0..200_000).flat_map(|_| {
+            let mut level = 9u32.to_le_bytes().to_vec();
+            level.extend(1u64.to_le_bytes());
+            level
+        }).collect();
+        let mut bytes = header(3, &[], &[]);
+        bytes[16..24].copy_from_slice(&1u64.to_le_bytes());
+        bytes.extend(string("general.nested"));
+        bytes.extend(9u32.to_le_bytes());
+        bytes.extend(nested);
+
+        // Same stack size as the async runtime's blocking threads
+        let result = std::thread::Builder::new()
+            .stack_size(2 * 1024 * 1024)
+            .spawn(move || inspect_bytes(&bytes).err().map(|e| e.code))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(result, Some(ErrorCode::Corrupt));
+    }
+}
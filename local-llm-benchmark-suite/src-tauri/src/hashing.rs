@@ -0,0 +1,166 @@
+use crate::backend::{BackendConfig, BackendKind};
+use crate::error::{AppError, ErrorCode};
+use crate::metrics::unix_millis;
+use crate::storage::Storage;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const CHUNK_BYTES: usize = 4 * 1024 * 1024;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+static JOB_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Serialize, Clone)]
+pub struct FileDigest {
+    pub path: String,
+    pub size: u64,
+    pub modified: u64,
+    pub sha256: String,
+    // True when the digest came from the cache instead of reading the file
+    pub cached: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct HashProgress {
+    pub job_id: String,
+    pub path: String,
+    pub bytes_hashed: u64,
+    pub total_bytes: u64,
+    pub done: bool,
+}
+
+// Cancellation flags of the hash jobs currently running, by job id
+#[derive(Default)]
+pub struct HashJobs {
+    jobs: Mutex<HashMap<String, Arc<AtomicBool>>>,
+}
+
+impl HashJobs {
+    pub fn start(&self, job_id: &str) -> Result<Arc<AtomicBool>, AppError> {
+        let mut jobs = self.jobs.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock hash jobs: {}", e)))?;
+        if jobs.contains_key(job_id) {
+            return Err(AppError::invalid_input(format!("Hash job {} is already running", job_id)));
+        }
+        let cancel = Arc::new(AtomicBool::new(false));
+        jobs.insert(job_id.to_string(), cancel.clone());
+        Ok(cancel)
+    }
+
+    pub fn finish(&self, job_id: &str) {
+        if let Ok(mut jobs) = self.jobs.lock() {
+            jobs.remove(job_id);
+        }
+    }
+
+    // Returns false when no job with this id is running
+    pub fn cancel(&self, job_id: &str) -> Result<bool, AppError> {
+        let jobs = self.jobs.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock hash jobs: {}", e)))?;
+        Ok(match jobs.get(job_id) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        })
+    }
+}
+
+pub fn new_job_id() -> String {
+    format!("hash-{}-{}", unix_millis(), JOB_COUNTER.fetch_add(1, Ordering::Relaxed))
+}
+
+// Streams the file through SHA-256, reusing the cached digest while the
+// file's size and modification time are unchanged.
+pub fn hash_file(
+    storage: &Storage,
+    path: &Path,
+    cancel: &AtomicBool,
+    on_progress: &mut dyn FnMut(u64, u64),
+) -> Result<FileDigest, AppError> {
+    let metadata = std::fs::metadata(path)
+        .map_err(|e| AppError::io(&format!("Failed to read {}", path.display()), e))?;
+    if !metadata.is_file() {
+        return Err(AppError::invalid_input(format!("{} is not a file", path.display())));
+    }
+    let path_str = path.display().to_string();
+    let size = metadata.len();
+    let modified = metadata.modified()
+        .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs())
+        .unwrap_or(0);
+
+    if let Some(sha256) = storage.cached_digest(&path_str, size, modified)? {
+        on_progress(size, size);
+        return Ok(FileDigest { path: path_str, size, modified, sha256, cached: true });
+    }
+
+    let mut file = File::open(path)
+        .map_err(|e| AppError::io(&format!("Failed to open {}", path.display()), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; CHUNK_BYTES];
+    let mut hashed = 0u64;
+    let mut last_progress = Instant::now();
+    on_progress(0, size);
+
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(AppError::cancelled(format!("Hashing {} was cancelled", path.display())));
+        }
+        let n = file.read(&mut buf)
+            .map_err(|e| AppError::io(&format!("Failed to read {}", path.display()), e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        hashed += n as u64;
+
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            on_progress(hashed, size);
+            last_progress = Instant::now();
+        }
+    }
+
+    let sha256 = format!("{:x}", hasher.finalize());
+    // A file rewritten while it was read has no meaningful digest
+    let changed = std::fs::metadata(path)
+        .map(|m| m.len() != size || m.modified().ok() != metadata.modified().ok())
+        .unwrap_or(true);
+    if changed || hashed != size {
+        return Err(AppError::new(
+            ErrorCode::Io,
+            format!("{} changed while it was being hashed", path.display()),
+        ));
+    }
+
+    storage.record_digest(&path_str, size, modified, &sha256)?;
+    on_progress(size, size);
+    Ok(FileDigest { path: path_str, size, modified, sha256, cached: false })
+}
+
+// Digest the backend reports for a model it serves, for benchmarks without a
+// local model file. Only Ollama exposes one.
+pub fn backend_digest(backend: &BackendConfig, model: &str) -> Option<String> {
+    if backend.kind != BackendKind::Ollama {
+        return None;
+    }
+
+    // Ollama resolves an untagged name to `:latest`
+    let tagged = format!("{}:latest", model);
+    backend.connect()
+        .list_models()
+        .ok()
+        .and_then(|models| models.into_iter().find(|m| m.id == model || m.id == tagged))
+        .and_then(|m| m.digest)
+        .map(|digest| match digest.contains(':') {
+            true => digest,
+            false => format!("sha256:{}", digest),
+        })
+}
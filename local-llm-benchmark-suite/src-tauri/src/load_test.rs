@@ -0,0 +1,412 @@
+use crate::backend::{BackendConfig, LlmBackend, SamplingOptions};
+use crate::benchmark::{self, BenchmarkRequest};
+use crate::error::AppError;
+use crate::stats::{self, Summary};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const MAX_CONCURRENCY: u32 = 256;
+const DEFAULT_BUCKET_MS: u64 = 1000;
+const MIN_BUCKET_MS: u64 = 100;
+// Bounds the timeline's memory whatever the schedule and bucket width
+const MAX_TIMELINE_BUCKETS: u64 = 10_000;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RampStep {
+    pub concurrency: u32,
+    pub duration_secs: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum LoadSchedule {
+    // `requests` in total, never more than `concurrency` in flight
+    Fixed { concurrency: u32, requests: u32 },
+    // Each step keeps `concurrency` requests in flight for `duration_secs`
+    Ramp { steps: Vec<RampStep> },
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LoadTestRequest {
+    pub backend: BackendConfig,
+    pub model: String,
+    pub prompt: String,
+    #[serde(default)]
+    pub system: Option<String>,
+    #[serde(default)]
+    pub options: SamplingOptions,
+    pub schedule: LoadSchedule,
+    #[serde(default)]
+    pub bucket_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LoadStepResult {
+    pub concurrency: u32,
+    pub requests: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    pub duration_ms: f64,
+    pub requests_per_second: f64,
+    pub tokens_per_second: f64,
+    pub latency_ms: Option<Summary>,
+    pub time_to_first_token_ms: Option<Summary>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TimelineBucket {
+    pub start_ms: u64,
+    pub completed: usize,
+    pub errors: usize,
+    pub error_rate: f64,
+    pub tokens_per_second: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct LoadTestResult {
+    pub model: String,
+    pub overall: LoadStepResult,
+    pub steps: Vec<LoadStepResult>,
+    pub timeline: Vec<TimelineBucket>,
+    pub sample_errors: Vec<AppError>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LoadTestProgress {
+    pub step: usize,
+    pub concurrency: u32,
+    pub completed: usize,
+    pub errors: usize,
+    pub elapsed_ms: f64,
+}
+
+struct Outcome {
+    step: usize,
+    started: Duration,
+    finished: Duration,
+    time_to_first_token_ms: Option<f64>,
+    tokens: u64,
+    error: Option<AppError>,
+}
+
+pub fn run(
+    request: &LoadTestRequest,
+    on_progress: &(dyn Fn(LoadTestProgress) + Sync),
+) -> Result<LoadTestResult, AppError> {
+    let steps = validate(&request.schedule)?;
+    let bucket_ms = bucket_ms(request)?;
+    let backend = request.backend.connect();
+    let outcomes: Mutex<Vec<Outcome>> = Mutex::new(Vec::new());
+    let start = Instant::now();
+
+    for (index, (concurrency, limit)) in steps.iter().enumerate() {
+        let remaining = AtomicU32::new(match limit {
+            StepLimit::Requests(n) => *n,
+            StepLimit::Duration(_) => u32::MAX,
+        });
+        let deadline = match limit {
+            StepLimit::Duration(d) => Some(Instant::now() + *d),
+            StepLimit::Requests(_) => None,
+        };
+
+        std::thread::scope(|scope| {
+            for _ in 0..*concurrency {
+                scope.spawn(|| loop {
+                    if deadline.is_some_and(|d| Instant::now() >= d) {
+                        break;
+                    }
+                    if remaining.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1)).is_err() {
+                        break;
+                    }
+
+                    let outcome = send_one(backend.as_ref(), request, index, start);
+                    let Ok(mut outcomes) = outcomes.lock() else {
+                        break;
+                    };
+                    outcomes.push(outcome);
+                    let in_step = outcomes.iter().filter(|o| o.step == index);
+                    let (completed, errors) = in_step.fold((0, 0), |(c, e), o| (c + 1, e + o.error.is_some() as usize));
+                    drop(outcomes);
+
+                    on_progress(LoadTestProgress {
+                        step: index,
+                        concurrency: *concurrency,
+                        completed,
+                        errors,
+                        elapsed_ms: start.elapsed().as_secs_f64() * 1000.0,
+                    });
+                });
+            }
+        });
+    }
+
+    let outcomes = outcomes.into_inner()
+        .map_err(|e| AppError::internal(format!("Failed to collect load test results: {}", e)))?;
+    let all: Vec<&Outcome> = outcomes.iter().collect();
+
+    let step_results = steps.iter().enumerate()
+        .map(|(index, (concurrency, _))| {
+            let in_step: Vec<&Outcome> = outcomes.iter().filter(|o| o.step == index).collect();
+            aggregate(*concurrency, &in_step)
+        })
+        .collect();
+    let peak_concurrency = steps.iter().map(|(c, _)| *c).max().unwrap_or(0);
+
+    Ok(LoadTestResult {
+        model: request.model.clone(),
+        overall: aggregate(peak_concurrency, &all),
+        steps: step_results,
+        timeline: timeline(&all, bucket_ms),
+        sample_errors: outcomes.iter()
+            .filter_map(|o| o.error.clone())
+            .take(10)
+            .collect(),
+    })
+}
+
+enum StepLimit {
+    Requests(u32),
+    Duration(Duration),
+}
+
+fn validate(schedule: &LoadSchedule) -> Result<Vec<(u32, StepLimit)>, AppError> {
+    let steps = match schedule {
+        LoadSchedule::Fixed { concurrency, requests } => {
+            if *requests == 0 {
+                return Err(AppError::invalid_input("Load test needs at least one request"));
+            }
+            vec![(*concurrency, StepLimit::Requests(*requests))]
+        }
+        LoadSchedule::Ramp { steps } => {
+            if steps.is_empty() {
+                return Err(AppError::invalid_input("Ramp schedule needs at least one step"));
+            }
+            if steps.iter().any(|s| s.duration_secs == 0) {
+                return Err(AppError::invalid_input("Ramp step duration must be greater than zero"));
+            }
+            steps.iter()
+                .map(|s| (s.concurrency, StepLimit::Duration(Duration::from_secs(s.duration_secs))))
+                .collect()
+        }
+    };
+
+    if steps.iter().any(|(c, _)| *c == 0 || *c > MAX_CONCURRENCY) {
+        return Err(AppError::invalid_input(format!("Concurrency must be between 1 and {}", MAX_CONCURRENCY)));
+    }
+    Ok(steps)
+}
+
+// A ramp's length is known up front, so a timeline that would need too many
+// buckets is refused before any request is sent
+fn bucket_ms(request: &LoadTestRequest) -> Result<u64, AppError> {
+    let bucket_ms = request.bucket_ms.unwrap_or(DEFAULT_BUCKET_MS);
+    if bucket_ms < MIN_BUCKET_MS {
+        return Err(AppError::invalid_input(format!("Timeline buckets must be at least {} ms", MIN_BUCKET_MS)));
+    }
+    if let LoadSchedule::Ramp { steps } = &request.schedule {
+        let total_ms = steps.iter()
+            .fold(0u64, |total, s| total.saturating_add(s.duration_secs.saturating_mul(1000)));
+        if total_ms / bucket_ms >= MAX_TIMELINE_BUCKETS {
+            return Err(AppError::invalid_input(format!(
+                "A {} s ramp in {} ms buckets needs more than {} timeline buckets; use wider buckets",
+                total_ms / 1000,
+                bucket_ms,
+                MAX_TIMELINE_BUCKETS
+            )));
+        }
+    }
+    Ok(bucket_ms)
+}
+
+fn send_one(backend: &dyn LlmBackend, request: &LoadTestRequest, step: usize, start: Instant) -> Outcome {
+    let run = BenchmarkRequest {
+        backend: request.backend.clone(),
+        model: request.model.clone(),
+        prompt: request.prompt.clone(),
+        system: request.system.clone(),
+        options: request.options.clone(),
+        run_id: Some(benchmark::new_run_id()),
+        model_path: None,
+        server: None,
+    };
+
+    let started = start.elapsed();
+    let result = benchmark::run_with(backend, &run, &mut |_| {});
+    let finished = start.elapsed();
+
+    match result {
+        Ok(result) => Outcome {
+            step,
+            started,
+            finished,
+            time_to_first_token_ms: result.time_to_first_token_ms,
+            tokens: result.completion_tokens.unwrap_or(result.output_chunks as u64),
+            error: None,
+        },
+        Err(e) => Outcome {
+            step,
+            started,
+            finished,
+            time_to_first_token_ms: None,
+            tokens: 0,
+            error: Some(e),
+        },
+    }
+}
+
+fn aggregate(concurrency: u32, outcomes: &[&Outcome]) -> LoadStepResult {
+    let errors = outcomes.iter().filter(|o| o.error.is_some()).count();
+    let succeeded: Vec<&&Outcome> = outcomes.iter().filter(|o| o.error.is_none()).collect();
+
+    let first = outcomes.iter().map(|o| o.started).min().unwrap_or_default();
+    let last = outcomes.iter().map(|o| o.finished).max().unwrap_or_default();
+    let window = (last.saturating_sub(first)).as_secs_f64();
+    let tokens: u64 = succeeded.iter().map(|o| o.tokens).sum();
+
+    let latencies: Vec<f64> = succeeded.iter()
+        .map(|o| (o.finished - o.started).as_secs_f64() * 1000.0)
+        .collect();
+    let ttfts: Vec<f64> = succeeded.iter().filter_map(|o| o.time_to_first_token_ms).collect();
+
+    LoadStepResult {
+        concurrency,
+        requests: outcomes.len(),
+        errors,
+        error_rate: ratio(errors, outcomes.len()),
+        duration_ms: window * 1000.0,
+        requests_per_second: if window > 0.0 { succeeded.len() as f64 / window } else { 0.0 },
+        tokens_per_second: if window > 0.0 { tokens as f64 / window } else { 0.0 },
+        latency_ms: stats::summarize(&latencies),
+        time_to_first_token_ms: stats::summarize(&ttfts),
+    }
+}
+
+// Buckets requests by completion time so error spikes and throughput drops
+// can be lined up with the ramp steps. Runs longer than planned get wider
+// buckets rather than more of them.
+fn timeline(outcomes: &[&Outcome], bucket_ms: u64) -> Vec<TimelineBucket> {
+    let Some(end) = outcomes.iter().map(|o| o.finished).max() else {
+        return Vec::new();
+    };
+    let bucket_ms = bucket_ms.max(end.as_millis() as u64 / MAX_TIMELINE_BUCKETS + 1);
+    let bucket_count = (end.as_millis() as u64 / bucket_ms + 1) as usize;
+    let mut buckets: Vec<(usize, usize, u64)> = vec![(0, 0, 0); bucket_count];
+
+    for outcome in outcomes {
+        let bucket = &mut buckets[(outcome.finished.as_millis() as u64 / bucket_ms) as usize];
+        bucket.0 += 1;
+        if outcome.error.is_some() {
+            bucket.1 += 1;
+        } else {
+            bucket.2 += outcome.tokens;
+        }
+    }
+
+    buckets.into_iter().enumerate()
+        .map(|(i, (completed, errors, tokens))| TimelineBucket {
+            start_ms: i as u64 * bucket_ms,
+            completed,
+            errors,
+            error_rate: ratio(errors, completed),
+            tokens_per_second: tokens as f64 / (bucket_ms as f64 / 1000.0),
+        })
+        .collect()
+}
+
+fn ratio(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 / whole as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::BackendKind;
+    use crate::error::ErrorCode;
+
+    fn request(schedule: LoadSchedule, bucket_ms: Option<u64>) -> LoadTestRequest {
+        LoadTestRequest {
+            backend: BackendConfig { kind: BackendKind::Ollama, base_url: None, api_key: None },
+            model: "llama3".to_string(),
+            prompt: "hi".to_string(),
+            system: None,
+            options: SamplingOptions::default(),
+            schedule,
+            bucket_ms,
+        }
+    }
+
+    fn ramp(steps: &[(u32, u64)]) -> LoadSchedule {
+        LoadSchedule::Ramp {
+            steps: steps.iter()
+                .map(|&(concurrency, duration_secs)| RampStep { concurrency, duration_secs })
+                .collect(),
+        }
+    }
+
+    fn outcome(finished_ms: u64, tokens: u64, failed: bool) -> Outcome {
+        Outcome {
+            step: 0,
+            started: Duration::ZERO,
+            finished: Duration::from_millis(finished_ms),
+            time_to_first_token_ms: None,
+            tokens,
+            error: failed.then(|| AppError::backend("boom")),
+        }
+    }
+
+    #[test]
+    fn schedules_are_validated() {
+        let invalid = |schedule: LoadSchedule| validate(&schedule).err().unwrap().code;
+        assert_eq!(invalid(LoadSchedule::Fixed { concurrency: 4, requests: 0 }), ErrorCode::InvalidInput);
+        assert_eq!(invalid(LoadSchedule::Fixed { concurrency: 0, requests: 5 }), ErrorCode::InvalidInput);
+        assert_eq!(invalid(ramp(&[])), ErrorCode::InvalidInput);
+        assert_eq!(invalid(ramp(&[(1, 10), (2, 0)])), ErrorCode::InvalidInput);
+        assert_eq!(invalid(ramp(&[(MAX_CONCURRENCY + 1, 10)])), ErrorCode::InvalidInput);
+
+        let steps = validate(&ramp(&[(1, 10), (MAX_CONCURRENCY, 5)])).unwrap();
+        assert_eq!(steps.iter().map(|(c, _)| *c).collect::<Vec<_>>(), [1, MAX_CONCURRENCY]);
+        assert!(matches!(steps[1].1, StepLimit::Duration(d) if d == Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn bucket_width_is_bounded() {
+        let fixed = || LoadSchedule::Fixed { concurrency: 1, requests: 1 };
+        assert_eq!(bucket_ms(&request(fixed(), None)).unwrap(), DEFAULT_BUCKET_MS);
+        assert!(bucket_ms(&request(fixed(), Some(1))).is_err());
+        assert_eq!(bucket_ms(&request(fixed(), Some(MIN_BUCKET_MS))).unwrap(), MIN_BUCKET_MS);
+
+        // 10 minutes in 100 ms buckets is 6000 buckets; a day is not
+        assert!(bucket_ms(&request(ramp(&[(1, 300), (2, 300)]), Some(100))).is_ok());
+        assert!(bucket_ms(&request(ramp(&[(1, 86_400)]), Some(100))).is_err());
+        assert!(bucket_ms(&request(ramp(&[(1, u64::MAX), (1, u64::MAX)]), Some(1000))).is_err());
+    }
+
+    #[test]
+    fn timeline_buckets_by_completion_time() {
+        let outcomes = [outcome(100, 10, false), outcome(900, 20, false), outcome(1200, 0, true), outcome(2500, 5, false)];
+        let refs: Vec<&Outcome> = outcomes.iter().collect();
+        let buckets = timeline(&refs, 1000);
+
+        let rows: Vec<(u64, usize, usize)> = buckets.iter().map(|b| (b.start_ms, b.completed, b.errors)).collect();
+        assert_eq!(rows, [(0, 2, 0), (1000, 1, 1), (2000, 1, 0)]);
+        assert_eq!(buckets[0].tokens_per_second, 30.0);
+        assert_eq!(buckets[1].error_rate, 1.0);
+        assert_eq!(buckets[2].tokens_per_second, 5.0);
+        assert!(timeline(&[], 1000).is_empty());
+    }
+
+    #[test]
+    fn long_runs_widen_the_buckets() {
+        let outcomes = [outcome(0, 1, false), outcome(3_600_000, 1, false)];
+        let refs: Vec<&Outcome> = outcomes.iter().collect();
+        let buckets = timeline(&refs, MIN_BUCKET_MS);
+        assert!(buckets.len() as u64 <= MAX_TIMELINE_BUCKETS);
+        assert_eq!(buckets.iter().map(|b| b.completed).sum::<usize>(), 2);
+    }
+}
@@ -1,229 +1,743 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
+mod atomic;
+mod backend;
+mod benchmark;
+mod error;
+mod files;
+mod fs_scope;
+mod gguf;
+mod hashing;
+mod load_test;
+mod metrics;
+mod models;
+mod process;
+mod resources;
+mod runner;
+mod server;
+mod sensors;
+mod stats;
+mod storage;
+mod suite;
+
+use backend::ollama::{
+    OllamaChatRequest, OllamaClient, OllamaGenerateRequest, OllamaModel, OllamaResponse, OllamaRunningModel,
+    OllamaShowResponse,
+};
+use backend::{BackendConfig, BackendKind, ChatRequest, ChatResponse, ModelInfo, StreamEvent};
+use benchmark::{BenchmarkRequest, BenchmarkResult};
+use error::AppError;
+use files::FileInfo;
+use fs_scope::{AllowedRoots, FsScope};
+use gguf::GgufInfo;
+use hashing::{FileDigest, HashJobs, HashProgress};
+use load_test::{LoadTestRequest, LoadTestResult};
+use metrics::{DiskInfo, MetricsSample, MetricsState, SamplerStatus, SystemMetrics};
+use models::{LibraryModel, ModelDir, ModelSource, ScanReport};
+use process::{ProcessMetrics, ProcessTarget};
+use runner::{CaseRequest, CaseResult, SuiteResult, SuiteRunRequest};
+use server::{LogLine, ServerBinaries, ServerBinaryList, ServerConfig, ServerGuard, ServerInfo, ServerManager};
 use std::collections::HashMap;
-use sysinfo::System;
-
-type MetricsHistory = Mutex<Vec<(std::time::Instant, SystemMetrics)>>;
-
-#[derive(Serialize, Deserialize, Clone)]
-pub struct SystemMetrics {
-    pub memory_used: f64,
-    pub memory_total: f64,
-    pub memory_percentage: f64,
-    pub cpu_usage: f64,
-    pub cpu_count: usize,
-    pub load_average: f64,
-    pub battery_level: Option<f64>,
-    pub battery_time_remaining: Option<String>,
-    pub battery_state: String,
-    pub disk_usage: Vec<DiskInfo>,
-    pub temperature: Option<f64>,
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-pub struct DiskInfo {
-    pub name: String,
-    pub total: u64,
-    pub available: u64,
-    pub used_percentage: f64,
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-pub struct StorageData {
-    pub key: String,
-    pub value: String,
-    pub timestamp: u64,
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct FileInfo {
-    pub name: String,
-    pub path: String,
-    pub size: u64,
-    pub is_directory: bool,
-    pub modified: u64,
-}
-
-#[tauri::command]
-async fn get_system_metrics() -> Result<SystemMetrics, String> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
-    // Memory metrics (GB)
-    let memory_used = sys.used_memory() as f64 / (1024.0 * 1024.0 * 1024.0);
-    let memory_total = sys.total_memory() as f64 / (1024.0 * 1024.0 * 1024.0);
-    let memory_percentage = (memory_used / memory_total) * 100.0;
-
-    // CPU metrics (simplified for sysinfo 0.30)
-    // In a real implementation, you'd track CPU usage over time
-    let cpu_usage = 0.0; // Placeholder - real implementation would track CPU usage
-    let cpu_count = sys.physical_core_count().unwrap_or(1);
-    let load_average = 0.0; // Simplified for sysinfo 0.30
-
-    // Disk metrics (simplified for sysinfo 0.30)
-    let mut disk_usage = Vec::new();
-
-    // Add a default disk entry for macOS/Linux
-    #[cfg(any(target_os = "macos", target_os = "linux"))]
-    {
-        disk_usage.push(DiskInfo {
-            name: "Main Disk".to_string(),
-            total: sys.total_memory() * 4, // Estimate based on memory
-            available: sys.available_memory(),
-            used_percentage: 50.0, // Placeholder
-        });
-    }
+use storage::{CompactionReport, RecoveryReport, Storage, StorageData, StorageLimits, StorageWarning, StoredRun};
+use suite::{BenchmarkSuite, SuiteValidation};
+use tauri::{AppHandle, Manager, State};
+
+#[tauri::command]
+async fn get_system_metrics(metrics: State<'_, MetricsState>) -> Result<SystemMetrics, AppError> {
+    metrics.snapshot()
+}
+
+#[tauri::command]
+async fn get_disk_for_path(metrics: State<'_, MetricsState>, path: String) -> Result<Option<DiskInfo>, AppError> {
+    metrics.disk_for_path(std::path::Path::new(&path))
+}
+
+#[tauri::command]
+async fn start_metrics_sampling(
+    metrics: State<'_, MetricsState>,
+    interval_ms: Option<u64>,
+    capacity: Option<usize>,
+) -> Result<SamplerStatus, AppError> {
+    metrics.start_sampling(interval_ms, capacity)
+}
 
-    // Battery metrics (simplified for sysinfo 0.30)
-    let mut battery_level: Option<f64> = None;
-    let mut battery_time_remaining: Option<String> = None;
-    let mut battery_state = "Unavailable".to_string();
-
-    // Temperature (if available via sysinfo 0.30)
-    let mut temperature: Option<f64> = None;
-
-    Ok(SystemMetrics {
-        memory_used,
-        memory_total,
-        memory_percentage,
-        cpu_usage,
-        cpu_count,
-        load_average,
-        battery_level,
-        battery_time_remaining,
-        battery_state,
-        disk_usage,
-        temperature,
+#[tauri::command]
+async fn stop_metrics_sampling(metrics: State<'_, MetricsState>) -> Result<SamplerStatus, AppError> {
+    metrics.stop_sampling()
+}
+
+#[tauri::command]
+async fn get_metrics_sampler_status(metrics: State<'_, MetricsState>) -> Result<SamplerStatus, AppError> {
+    metrics.status()
+}
+
+#[tauri::command]
+async fn get_metrics_history(
+    metrics: State<'_, MetricsState>,
+    since: Option<u64>,
+    until: Option<u64>,
+) -> Result<Vec<MetricsSample>, AppError> {
+    metrics.history_window(since, until)
+}
+
+#[tauri::command]
+async fn get_process_metrics(
+    metrics: State<'_, MetricsState>,
+    target: ProcessTarget,
+) -> Result<ProcessMetrics, AppError> {
+    metrics.process_metrics(&target)
+}
+
+#[tauri::command]
+async fn track_process(
+    metrics: State<'_, MetricsState>,
+    target: ProcessTarget,
+) -> Result<ProcessMetrics, AppError> {
+    metrics.track_process(target)
+}
+
+#[tauri::command]
+async fn untrack_process(metrics: State<'_, MetricsState>) -> Result<(), AppError> {
+    metrics.untrack_process()
+}
+
+#[tauri::command]
+async fn clear_metrics_history(metrics: State<'_, MetricsState>) -> Result<(), AppError> {
+    metrics.clear_history()
+}
+
+#[tauri::command]
+async fn ollama_list_models(base_url: Option<String>) -> Result<Vec<OllamaModel>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        OllamaClient::new(base_url.as_deref()).list_models()
     })
+    .await
+    .map_err(|e| AppError::internal(format!("Ollama task failed: {}", e)))?
 }
 
 #[tauri::command]
-async fn read_file(path: String) -> Result<String, String> {
-    std::fs::read_to_string(path)
-        .map_err(|e| format!("Failed to read file: {}", e))
+async fn ollama_pull_model(app: AppHandle, base_url: Option<String>, model: String) -> Result<(), AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        OllamaClient::new(base_url.as_deref()).pull(&model, |progress| {
+            let _ = app.emit_all("ollama-pull-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Ollama task failed: {}", e)))?
 }
 
 #[tauri::command]
-async fn write_file(path: String, content: String) -> Result<(), String> {
-    std::fs::write(path, content)
-        .map_err(|e| format!("Failed to write file: {}", e))
+async fn ollama_delete_model(base_url: Option<String>, model: String) -> Result<(), AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        OllamaClient::new(base_url.as_deref()).delete(&model)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Ollama task failed: {}", e)))?
 }
 
 #[tauri::command]
-async fn list_directory(path: String) -> Result<Vec<FileInfo>, String> {
-    let entries = std::fs::read_dir(path)
-        .map_err(|e| format!("Failed to read directory: {}", e))?;
+async fn ollama_show_model(base_url: Option<String>, model: String) -> Result<OllamaShowResponse, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        OllamaClient::new(base_url.as_deref()).show(&model)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Ollama task failed: {}", e)))?
+}
 
-    let mut files = Vec::new();
-    for entry in entries {
-        let entry = entry.map_err(|e| format!("Failed to read entry: {}", e))?;
-        let metadata = entry.metadata()
-            .map_err(|e| format!("Failed to get metadata: {}", e))?;
+#[tauri::command]
+async fn ollama_running_models(base_url: Option<String>) -> Result<Vec<OllamaRunningModel>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        OllamaClient::new(base_url.as_deref()).running_models()
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Ollama task failed: {}", e)))?
+}
 
-        files.push(FileInfo {
-            name: entry.file_name().to_string_lossy().to_string(),
-            path: entry.path().to_string_lossy().to_string(),
-            size: metadata.len(),
-            is_directory: metadata.is_dir(),
-            modified: metadata.modified()
-                .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs())
-                .unwrap_or(0),
+#[tauri::command]
+async fn ollama_generate(
+    app: AppHandle,
+    base_url: Option<String>,
+    request: OllamaGenerateRequest,
+    stream_id: Option<String>,
+) -> Result<OllamaResponse, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = OllamaClient::new(base_url.as_deref());
+        let response = client.generate(&request, |token| {
+            emit_stream(&app, &stream_id, token, false);
         });
-    }
+        emit_stream(&app, &stream_id, "", true);
+        response
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Ollama task failed: {}", e)))?
+}
 
-    Ok(files)
+#[tauri::command]
+async fn ollama_chat(
+    app: AppHandle,
+    base_url: Option<String>,
+    request: OllamaChatRequest,
+    stream_id: Option<String>,
+) -> Result<OllamaResponse, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let client = OllamaClient::new(base_url.as_deref());
+        let response = client.chat(&request, |token| {
+            emit_stream(&app, &stream_id, token, false);
+        });
+        emit_stream(&app, &stream_id, "", true);
+        response
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Ollama task failed: {}", e)))?
 }
 
 #[tauri::command]
-async fn store_data(key: String, value: String) -> Result<(), String> {
-    let data_dir = dirs::data_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("local-llm-benchmark-suite");
+async fn list_backend_models(backend: BackendConfig) -> Result<Vec<ModelInfo>, AppError> {
+    tauri::async_runtime::spawn_blocking(move || backend.connect().list_models())
+        .await
+        .map_err(|e| AppError::internal(format!("Backend task failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn backend_chat(
+    app: AppHandle,
+    backend: BackendConfig,
+    request: ChatRequest,
+    stream_id: Option<String>,
+) -> Result<ChatResponse, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = backend.connect().chat(&request, &mut |token| {
+            emit_stream(&app, &stream_id, token, false);
+        });
+        emit_stream(&app, &stream_id, "", true);
+        response
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Backend task failed: {}", e)))?
+}
 
-    std::fs::create_dir_all(&data_dir)
-        .map_err(|e| format!("Failed to create data directory: {}", e))?;
+#[tauri::command]
+async fn run_benchmark(app: AppHandle, mut request: BenchmarkRequest) -> Result<BenchmarkResult, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let _server = attach_server(&app, &request.server, &mut request.backend, &mut request.model_path)?;
+        let model_digest = model_digest(&app, &request.backend, &request.model, &request.model_path)?;
+        let metrics = app.state::<MetricsState>();
+        let mut result = benchmark::run_monitored(&metrics, &request, &mut |progress| {
+            let _ = app.emit_all("benchmark-progress", progress);
+        })?;
+        result.model_digest = model_digest;
+
+        let storage = app.state::<Storage>();
+        let hardware_id = storage.record_hardware(&metrics.hardware_snapshot()?)?;
+        storage.record_run(&result, Some(hardware_id))?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Benchmark task failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn run_benchmark_case(app: AppHandle, mut request: CaseRequest) -> Result<CaseResult, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let _server = attach_server(&app, &request.server, &mut request.backend, &mut request.model_path)?;
+        let model_digest = model_digest(&app, &request.backend, &request.model, &request.model_path)?;
+        let metrics = app.state::<MetricsState>();
+        let mut result = runner::run_case(
+            &metrics,
+            &request,
+            &mut |case| {
+                let _ = app.emit_all("benchmark-case-progress", case);
+            },
+            &mut |progress| {
+                let _ = app.emit_all("benchmark-progress", progress);
+            },
+        )?;
+        for run in &mut result.runs {
+            run.model_digest = model_digest.clone();
+        }
+
+        let storage = app.state::<Storage>();
+        let hardware_id = storage.record_hardware(&metrics.hardware_snapshot()?)?;
+        storage.record_case(None, request.backend.kind, &result, Some(hardware_id))?;
+        Ok(result)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Benchmark task failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn run_benchmark_suite(app: AppHandle, mut request: SuiteRunRequest) -> Result<SuiteResult, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let _server = attach_server(&app, &request.server, &mut request.backend, &mut request.model_path)?;
+        let model_digest = model_digest(&app, &request.backend, &request.model, &request.model_path)?;
+        let metrics = app.state::<MetricsState>();
+        let mut result = runner::run_suite(
+            &metrics,
+            &request,
+            &mut |case| {
+                let _ = app.emit_all("benchmark-case-progress", case);
+            },
+            &mut |progress| {
+                let _ = app.emit_all("benchmark-progress", progress);
+            },
+        )?;
+        for run in result.cases.iter_mut().flat_map(|case| case.runs.iter_mut()) {
+            run.model_digest = model_digest.clone();
+        }
+
+        let storage = app.state::<Storage>();
+        let hardware_id = storage.record_hardware(&metrics.hardware_snapshot()?)?;
+        for case in &result.cases {
+            storage.record_case(Some(&result.suite), request.backend.kind, case, Some(hardware_id))?;
+        }
+        Ok(result)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Benchmark task failed: {}", e)))?
+}
 
-    // Simple file-based storage
-    let storage_file = data_dir.join("storage.json");
-    let mut storage: HashMap<String, StorageData> =
-        if storage_file.exists() {
-            let content = std::fs::read_to_string(&storage_file)
-                .map_err(|e| format!("Failed to read storage: {}", e))?;
-            serde_json::from_str(&content)
-                .unwrap_or_default()
-        } else {
-            HashMap::new()
-        };
+// Launches the request's server, if any, and points the backend at it. The
+// server is stopped when the returned guard is dropped.
+fn attach_server<'a>(
+    app: &'a AppHandle,
+    config: &Option<ServerConfig>,
+    backend: &mut BackendConfig,
+    model_path: &mut Option<String>,
+) -> Result<Option<ServerGuard<'a>>, AppError> {
+    let Some(config) = config else {
+        return Ok(None);
+    };
+    let info = launch_server(app, config)?;
+    // llama-server serves the OpenAI API under /v1, like the client's default URL
+    backend.base_url = Some(match backend.kind {
+        BackendKind::OpenAi => backend::join_url(&info.base_url, "/v1"),
+        BackendKind::Ollama => info.base_url.clone(),
+    });
+    if model_path.is_none() {
+        model_path.clone_from(&config.model_path);
+    }
+    Ok(Some(ServerGuard::new(app.state::<ServerManager>().inner(), info)))
+}
 
-    let timestamp = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
+// The binary and whether it may listen beyond loopback come from
+// servers.json; the model and any file paths in the arguments must live
+// inside the allowed roots, like every other path the frontend hands us.
+fn launch_server(app: &AppHandle, config: &ServerConfig) -> Result<ServerInfo, AppError> {
+    let scope = app.state::<FsScope>();
+    let binaries = app.state::<ServerBinaries>();
+    let binary = binaries.resolve(config.binary.as_deref(), &scope)?;
+    binaries.check_host(config.host.as_deref())?;
+    let model_path = match &config.model_path {
+        Some(path) => Some(scope.resolve(path)?),
+        None => None,
+    };
+    let config = server::scoped_config(config, &scope)?;
+    app.state::<ServerManager>().start(&config, &binary, model_path.as_deref())
+}
 
-    storage.insert(key.clone(), StorageData { key, value, timestamp });
+#[tauri::command]
+async fn start_server(app: AppHandle, config: ServerConfig) -> Result<ServerInfo, AppError> {
+    tauri::async_runtime::spawn_blocking(move || launch_server(&app, &config))
+        .await
+        .map_err(|e| AppError::internal(format!("Server task failed: {}", e)))?
+}
 
-    let content = serde_json::to_string_pretty(&storage)
-        .map_err(|e| format!("Failed to serialize storage: {}", e))?;
+#[tauri::command]
+async fn list_server_binaries(binaries: State<'_, ServerBinaries>) -> Result<ServerBinaryList, AppError> {
+    Ok(binaries.list())
+}
 
-    std::fs::write(&storage_file, content)
-        .map_err(|e| format!("Failed to write storage: {}", e))
+#[tauri::command]
+async fn stop_server(servers: State<'_, ServerManager>, id: String) -> Result<ServerInfo, AppError> {
+    servers.stop(&id)
 }
 
 #[tauri::command]
-async fn retrieve_data(key: String) -> Result<Option<StorageData>, String> {
-    let data_dir = dirs::data_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("local-llm-benchmark-suite");
+async fn list_servers(servers: State<'_, ServerManager>) -> Result<Vec<ServerInfo>, AppError> {
+    servers.list()
+}
 
-    let storage_file = data_dir.join("storage.json");
+#[tauri::command]
+async fn get_server_logs(
+    servers: State<'_, ServerManager>,
+    id: String,
+    limit: Option<usize>,
+) -> Result<Vec<LogLine>, AppError> {
+    servers.logs(&id, limit)
+}
 
-    if !storage_file.exists() {
-        return Ok(None);
+// Identifies the exact weights behind a benchmark: the SHA-256 of the local
+// model file when one is given, otherwise the digest the backend reports.
+// Resolved before the run so it describes the weights that were benchmarked;
+// hashing a new file reports `hash-progress` and can be cancelled like any
+// other hash job.
+fn model_digest(
+    app: &AppHandle,
+    backend: &BackendConfig,
+    model: &str,
+    model_path: &Option<String>,
+) -> Result<Option<String>, AppError> {
+    match model_path {
+        Some(path) => {
+            let path = app.state::<FsScope>().resolve(path)?;
+            let digest = run_hash_job(app, &path, hashing::new_job_id())?;
+            Ok(Some(format!("sha256:{}", digest.sha256)))
+        }
+        None => Ok(hashing::backend_digest(backend, model)),
     }
+}
 
-    let content = std::fs::read_to_string(&storage_file)
-        .map_err(|e| format!("Failed to read storage: {}", e))?;
+// Hashes a file as a cancellable job, emitting `hash-progress` throughout
+fn run_hash_job(app: &AppHandle, path: &std::path::Path, job_id: String) -> Result<FileDigest, AppError> {
+    let jobs = app.state::<HashJobs>();
+    let cancel = jobs.start(&job_id)?;
+    let path_str = path.display().to_string();
+    let result = hashing::hash_file(&app.state::<Storage>(), path, &cancel, &mut |bytes_hashed, total_bytes| {
+        let _ = app.emit_all("hash-progress", HashProgress {
+            job_id: job_id.clone(),
+            path: path_str.clone(),
+            bytes_hashed,
+            total_bytes,
+            done: false,
+        });
+    });
+    jobs.finish(&job_id);
+
+    let (bytes_hashed, total_bytes) = match &result {
+        Ok(digest) => (digest.size, digest.size),
+        Err(_) => (0, 0),
+    };
+    let _ = app.emit_all("hash-progress", HashProgress {
+        job_id,
+        path: path_str,
+        bytes_hashed,
+        total_bytes,
+        done: true,
+    });
+    result
+}
 
-    let storage: HashMap<String, StorageData> = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse storage: {}", e))?;
+#[tauri::command]
+async fn run_load_test(app: AppHandle, request: LoadTestRequest) -> Result<LoadTestResult, AppError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        load_test::run(&request, &|progress| {
+            let _ = app.emit_all("load-test-progress", progress);
+        })
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Load test task failed: {}", e)))?
+}
 
-    Ok(storage.get(&key).cloned())
+#[tauri::command]
+async fn load_benchmark_suite(scope: State<'_, FsScope>, path: String) -> Result<BenchmarkSuite, AppError> {
+    suite::load(&scope.resolve(&path)?)
 }
 
 #[tauri::command]
-async fn get_storage_keys() -> Result<Vec<String>, String> {
-    let data_dir = dirs::data_dir()
-        .unwrap_or_else(|| std::path::PathBuf::from("."))
-        .join("local-llm-benchmark-suite");
+async fn validate_benchmark_suite(content: String) -> Result<SuiteValidation, AppError> {
+    Ok(suite::validate(&content))
+}
+
+fn emit_stream(app: &AppHandle, stream_id: &Option<String>, content: &str, done: bool) {
+    let _ = app.emit_all("backend-stream", StreamEvent {
+        stream_id: stream_id.clone(),
+        content: content.to_string(),
+        done,
+    });
+}
+
+#[tauri::command]
+async fn read_file(scope: State<'_, FsScope>, path: String) -> Result<String, AppError> {
+    let path = scope.resolve(&path)?;
+    std::fs::read_to_string(path)
+        .map_err(|e| AppError::io("Failed to read file", e))
+}
+
+#[tauri::command]
+async fn write_file(scope: State<'_, FsScope>, path: String, content: String) -> Result<(), AppError> {
+    let path = scope.resolve_for_write(&path)?;
+    atomic::write(&storage::app_data_dir(), &path, content.as_bytes())
+}
+
+#[tauri::command]
+async fn list_directory(scope: State<'_, FsScope>, path: String) -> Result<Vec<FileInfo>, AppError> {
+    files::list(&scope.resolve(&path)?)
+}
+
+#[tauri::command]
+async fn get_allowed_roots(scope: State<'_, FsScope>) -> Result<AllowedRoots, AppError> {
+    Ok(scope.allowed_roots())
+}
+
+#[tauri::command]
+async fn inspect_gguf(scope: State<'_, FsScope>, path: String) -> Result<GgufInfo, AppError> {
+    let path = scope.resolve(&path)?;
+    tauri::async_runtime::spawn_blocking(move || gguf::inspect(&path))
+        .await
+        .map_err(|e| AppError::internal(format!("Inspect task failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn hash_file(app: AppHandle, path: String, job_id: Option<String>) -> Result<FileDigest, AppError> {
+    let path = app.state::<FsScope>().resolve(&path)?;
+    let job_id = job_id.unwrap_or_else(hashing::new_job_id);
 
-    let storage_file = data_dir.join("storage.json");
+    tauri::async_runtime::spawn_blocking(move || run_hash_job(&app, &path, job_id))
+    .await
+    .map_err(|e| AppError::internal(format!("Hash task failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn cancel_hash(jobs: State<'_, HashJobs>, job_id: String) -> Result<bool, AppError> {
+    jobs.cancel(&job_id)
+}
 
-    if !storage_file.exists() {
-        return Ok(Vec::new());
+#[tauri::command]
+async fn scan_model_library(app: AppHandle, paths: Option<Vec<String>>) -> Result<ScanReport, AppError> {
+    let scope = app.state::<FsScope>();
+    let mut dirs = models::default_dirs();
+    dirs.extend(scope.custom_roots().into_iter().map(|path| ModelDir { path, source: ModelSource::Custom }));
+    for path in paths.unwrap_or_default() {
+        dirs.push(ModelDir { path: scope.resolve(&path)?, source: ModelSource::Custom });
     }
 
-    let content = std::fs::read_to_string(&storage_file)
-        .map_err(|e| format!("Failed to read storage: {}", e))?;
+    tauri::async_runtime::spawn_blocking(move || {
+        let storage = app.state::<Storage>();
+        models::scan(&storage, &dirs)
+    })
+    .await
+    .map_err(|e| AppError::internal(format!("Scan task failed: {}", e)))?
+}
+
+#[tauri::command]
+async fn list_model_library(storage: State<'_, Storage>) -> Result<Vec<LibraryModel>, AppError> {
+    storage.model_library()
+}
+
+#[tauri::command]
+async fn store_data(
+    storage: State<'_, Storage>,
+    key: String,
+    value: String,
+    namespace: Option<String>,
+    ttl_secs: Option<u64>,
+) -> Result<(), AppError> {
+    storage.put(namespace_or_default(&namespace), &key, &value, ttl_secs)
+}
+
+#[tauri::command]
+async fn store_many(
+    storage: State<'_, Storage>,
+    entries: HashMap<String, String>,
+    namespace: Option<String>,
+    ttl_secs: Option<u64>,
+) -> Result<(), AppError> {
+    storage.put_many(namespace_or_default(&namespace), &entries, ttl_secs)
+}
+
+#[tauri::command]
+async fn retrieve_data(
+    storage: State<'_, Storage>,
+    key: String,
+    namespace: Option<String>,
+) -> Result<Option<StorageData>, AppError> {
+    storage.get(namespace_or_default(&namespace), &key)
+}
+
+#[tauri::command]
+async fn retrieve_many(
+    storage: State<'_, Storage>,
+    keys: Vec<String>,
+    namespace: Option<String>,
+) -> Result<Vec<StorageData>, AppError> {
+    storage.get_many(namespace_or_default(&namespace), &keys)
+}
+
+#[tauri::command]
+async fn get_storage_keys(storage: State<'_, Storage>, namespace: Option<String>) -> Result<Vec<String>, AppError> {
+    storage.keys(namespace_or_default(&namespace), None)
+}
+
+#[tauri::command]
+async fn list_keys(
+    storage: State<'_, Storage>,
+    prefix: Option<String>,
+    namespace: Option<String>,
+) -> Result<Vec<String>, AppError> {
+    storage.keys(namespace_or_default(&namespace), prefix.as_deref())
+}
+
+#[tauri::command]
+async fn list_namespaces(storage: State<'_, Storage>) -> Result<Vec<String>, AppError> {
+    storage.namespaces()
+}
+
+#[tauri::command]
+async fn delete_data(storage: State<'_, Storage>, key: String, namespace: Option<String>) -> Result<bool, AppError> {
+    storage.delete(namespace_or_default(&namespace), &key)
+}
+
+#[tauri::command]
+async fn clear_namespace(storage: State<'_, Storage>, namespace: String) -> Result<usize, AppError> {
+    storage.clear_namespace(&namespace)
+}
+
+#[tauri::command]
+async fn get_storage_limits(storage: State<'_, Storage>) -> Result<StorageLimits, AppError> {
+    storage.limits()
+}
+
+#[tauri::command]
+async fn set_storage_limits(storage: State<'_, Storage>, limits: StorageLimits) -> Result<(), AppError> {
+    storage.set_limits(&limits)
+}
+
+#[tauri::command]
+async fn compact_storage(storage: State<'_, Storage>) -> Result<CompactionReport, AppError> {
+    storage.compact()
+}
 
-    let storage: HashMap<String, StorageData> = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse storage: {}", e))?;
+fn namespace_or_default(namespace: &Option<String>) -> &str {
+    namespace.as_deref().unwrap_or(storage::DEFAULT_NAMESPACE)
+}
 
-    Ok(storage.keys().cloned().collect())
+#[tauri::command]
+async fn get_storage_warnings(storage: State<'_, Storage>) -> Result<Vec<StorageWarning>, AppError> {
+    Ok(storage.warnings().to_vec())
+}
+
+#[tauri::command]
+async fn recover_storage(storage: State<'_, Storage>) -> Result<Vec<RecoveryReport>, AppError> {
+    storage.recover()
+}
+
+#[tauri::command]
+async fn list_benchmark_runs(
+    storage: State<'_, Storage>,
+    model: Option<String>,
+    limit: Option<u32>,
+) -> Result<Vec<StoredRun>, AppError> {
+    storage.list_runs(model.as_deref(), limit.unwrap_or(100))
+}
+
+#[tauri::command]
+async fn get_run_samples(storage: State<'_, Storage>, run_id: String) -> Result<Vec<serde_json::Value>, AppError> {
+    storage.run_samples(&run_id)
 }
 
 fn main() {
     tauri::Builder::default()
+        .manage(MetricsState::new())
+        .manage(HashJobs::default())
+        .manage(ServerManager::default())
+        .setup(|app| {
+            app.manage(Storage::open_default()?);
+            app.manage(FsScope::load(&storage::app_data_dir()));
+            app.manage(ServerBinaries::load(&storage::app_data_dir()));
+            metrics::spawn_sampler(app.handle());
+            Ok(())
+        })
+        // Sent once the page has loaded, since nothing listens during setup;
+        // `get_storage_warnings` returns the same list on demand
+        .on_page_load(|window, _| {
+            for warning in window.state::<Storage>().warnings() {
+                let _ = window.emit("storage-warning", warning);
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             get_system_metrics,
+            get_disk_for_path,
+            start_metrics_sampling,
+            stop_metrics_sampling,
+            get_metrics_sampler_status,
+            get_metrics_history,
+            clear_metrics_history,
+            get_process_metrics,
+            track_process,
+            untrack_process,
+            ollama_list_models,
+            ollama_pull_model,
+            ollama_delete_model,
+            ollama_show_model,
+            ollama_running_models,
+            start_server,
+            list_server_binaries,
+            stop_server,
+            list_servers,
+            get_server_logs,
+            ollama_generate,
+            ollama_chat,
+            list_backend_models,
+            backend_chat,
+            run_benchmark,
+            run_benchmark_case,
+            run_benchmark_suite,
+            run_load_test,
+            load_benchmark_suite,
+            validate_benchmark_suite,
             read_file,
             write_file,
             list_directory,
+            get_allowed_roots,
+            inspect_gguf,
+            hash_file,
+            cancel_hash,
+            scan_model_library,
+            list_model_library,
             store_data,
+            store_many,
             retrieve_data,
-            get_storage_keys
+            retrieve_many,
+            get_storage_keys,
+            list_keys,
+            list_namespaces,
+            delete_data,
+            clear_namespace,
+            get_storage_limits,
+            set_storage_limits,
+            compact_storage,
+            get_storage_warnings,
+            recover_storage,
+            list_benchmark_runs,
+            get_run_samples
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Launched servers would otherwise outlive the app
+            if let tauri::RunEvent::Exit = event {
+                app.state::<ServerManager>().stop_all();
+            }
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_store_data_calls_are_not_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = tauri::test::mock_app();
+        app.manage(Storage::open_in(dir.path()).unwrap());
+        let handle = app.handle();
+
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let handle = handle.clone();
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        let namespace = (thread % 2 == 0).then(|| "even".to_string());
+                        let stored = store_data(handle.state::<Storage>(), format!("t{}-k{}", thread, i), i.to_string(), namespace, None);
+                        tauri::async_runtime::block_on(stored).unwrap();
+                    }
+                });
+            }
+        });
+
+        let storage = handle.state::<Storage>();
+        assert_eq!(storage.keys(storage::DEFAULT_NAMESPACE, None).unwrap().len(), 4 * 50);
+        assert_eq!(storage.keys("even", None).unwrap().len(), 4 * 50);
+        assert_eq!(storage.get("even", "t0-k49").unwrap().unwrap().value, "49");
+    }
 }
@@ -0,0 +1,507 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::error::AppError;
+use crate::process::{self, ProcessMetrics, ProcessTarget};
+use crate::sensors::{self, BatteryInfo, SensorReader, ThermalZone};
+use sysinfo::{Components, DiskKind, Disks, System};
+use tauri::{AppHandle, Manager};
+
+// CPU usage is the delta between two refreshes, so the refresh loop must
+// never run faster than sysinfo's own minimum.
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(1000);
+pub const DEFAULT_HISTORY_CAPACITY: usize = 3600;
+
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SystemMetrics {
+    pub memory_used: f64,
+    pub memory_total: f64,
+    pub memory_percentage: f64,
+    pub memory_available: f64,
+    pub swap_used: f64,
+    pub swap_total: f64,
+    pub swap_percentage: f64,
+    pub cpu_usage: f64,
+    pub cpu_per_core: Vec<f64>,
+    pub cpu_count: usize,
+    pub load_average: f64,
+    pub load_average_5m: f64,
+    pub load_average_15m: f64,
+    pub battery_level: Option<f64>,
+    pub battery_time_remaining: Option<String>,
+    pub battery_state: String,
+    pub disk_usage: Vec<DiskInfo>,
+    pub temperature: Option<f64>,
+    pub thermal_zones: Vec<ThermalZone>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct DiskInfo {
+    pub name: String,
+    pub mount_point: String,
+    pub file_system: String,
+    pub kind: String,
+    pub is_removable: bool,
+    pub total: u64,
+    pub available: u64,
+    pub used: u64,
+    pub used_percentage: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HardwareSnapshot {
+    pub host_name: Option<String>,
+    pub os_version: Option<String>,
+    pub cpu_brand: String,
+    pub logical_cores: usize,
+    pub metrics: SystemMetrics,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MetricsSample {
+    #[serde(skip)]
+    pub instant: Instant,
+    pub timestamp: u64,
+    pub metrics: SystemMetrics,
+    pub process: Option<ProcessMetrics>,
+}
+
+pub type MetricsHistory = Mutex<VecDeque<MetricsSample>>;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SamplerConfig {
+    pub interval_ms: u64,
+    pub capacity: usize,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SamplerStatus {
+    pub sampling: bool,
+    pub interval_ms: u64,
+    pub capacity: usize,
+    pub samples: usize,
+    pub tracked_process: Option<ProcessTarget>,
+}
+
+pub struct MetricsState {
+    pub system: Mutex<System>,
+    pub disks: Mutex<Disks>,
+    pub components: Mutex<Components>,
+    pub sensors: SensorReader,
+    pub history: MetricsHistory,
+    // Sampling requested by the user; benchmark runs hold it separately
+    pub sampling: AtomicBool,
+    pub run_holds: AtomicUsize,
+    pub config: Mutex<SamplerConfig>,
+    pub tracked: Mutex<Option<ProcessTarget>>,
+}
+
+impl MetricsState {
+    pub fn new() -> Self {
+        let mut system = System::new_all();
+        system.refresh_cpu();
+
+        Self {
+            system: Mutex::new(system),
+            disks: Mutex::new(Disks::new_with_refreshed_list()),
+            components: Mutex::new(Components::new_with_refreshed_list()),
+            sensors: SensorReader::new(),
+            history: Mutex::new(VecDeque::new()),
+            sampling: AtomicBool::new(false),
+            run_holds: AtomicUsize::new(0),
+            config: Mutex::new(SamplerConfig {
+                interval_ms: REFRESH_INTERVAL.as_millis() as u64,
+                capacity: DEFAULT_HISTORY_CAPACITY,
+            }),
+            tracked: Mutex::new(None),
+        }
+    }
+
+    pub fn snapshot(&self) -> Result<SystemMetrics, AppError> {
+        let sys = self.system.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock system state: {}", e)))?;
+
+        self.collect_with(&sys)
+    }
+
+    pub fn hardware_snapshot(&self) -> Result<HardwareSnapshot, AppError> {
+        let sys = self.system.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock system state: {}", e)))?;
+
+        Ok(HardwareSnapshot {
+            host_name: System::host_name(),
+            os_version: System::long_os_version(),
+            cpu_brand: sys.cpus().first().map(|c| c.brand().trim().to_string()).unwrap_or_default(),
+            logical_cores: sys.cpus().len(),
+            metrics: self.collect_with(&sys)?,
+        })
+    }
+
+    fn collect_with(&self, sys: &System) -> Result<SystemMetrics, AppError> {
+        let mut disks = self.disks.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock disk state: {}", e)))?;
+        disks.refresh_list();
+
+        let mut components = self.components.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock component state: {}", e)))?;
+        components.refresh();
+
+        let mut thermal_zones = self.sensors.thermal_zones();
+        thermal_zones.extend(sensors::component_zones(&components));
+
+        Ok(collect(sys, &disks, self.sensors.battery(), thermal_zones))
+    }
+
+    // Picks the mount that would hold `path`, walking up to the nearest
+    // existing ancestor so it also works for files that are not yet downloaded.
+    pub fn disk_for_path(&self, path: &Path) -> Result<Option<DiskInfo>, AppError> {
+        let existing = path.ancestors()
+            .find(|p| p.exists())
+            .ok_or_else(|| AppError::not_found(format!("No existing ancestor for path: {}", path.display())))?;
+        let resolved = existing.canonicalize()
+            .map_err(|e| AppError::io("Failed to resolve path", e))?;
+
+        let mut disks = self.disks.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock disk state: {}", e)))?;
+        disks.refresh_list();
+
+        Ok(disks.list().iter()
+            .filter(|d| resolved.starts_with(d.mount_point()))
+            .max_by_key(|d| d.mount_point().as_os_str().len())
+            .map(disk_info))
+    }
+
+    pub fn start_sampling(&self, interval_ms: Option<u64>, capacity: Option<usize>) -> Result<SamplerStatus, AppError> {
+        {
+            let mut config = self.config.lock()
+                .map_err(|e| AppError::internal(format!("Failed to lock sampler config: {}", e)))?;
+            if let Some(interval_ms) = interval_ms {
+                config.interval_ms = interval_ms.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL.as_millis() as u64);
+            }
+            if let Some(capacity) = capacity {
+                if capacity == 0 {
+                    return Err(AppError::invalid_input("History capacity must be greater than zero"));
+                }
+                config.capacity = capacity;
+            }
+
+            let mut history = self.history.lock()
+                .map_err(|e| AppError::internal(format!("Failed to lock metrics history: {}", e)))?;
+            let excess = history.len().saturating_sub(config.capacity);
+            history.drain(..excess);
+        }
+
+        self.sampling.store(true, Ordering::SeqCst);
+        self.status()
+    }
+
+    pub fn stop_sampling(&self) -> Result<SamplerStatus, AppError> {
+        self.sampling.store(false, Ordering::SeqCst);
+        self.status()
+    }
+
+    pub fn status(&self) -> Result<SamplerStatus, AppError> {
+        let config = self.config.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock sampler config: {}", e)))?
+            .clone();
+        let samples = self.history.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock metrics history: {}", e)))?
+            .len();
+        let tracked_process = self.tracked.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock tracked process: {}", e)))?
+            .clone();
+
+        Ok(SamplerStatus {
+            sampling: self.is_sampling(),
+            interval_ms: config.interval_ms,
+            capacity: config.capacity,
+            samples,
+            tracked_process,
+        })
+    }
+
+    // `since` and `until` are unix timestamps in milliseconds, both inclusive.
+    pub fn history_window(&self, since: Option<u64>, until: Option<u64>) -> Result<Vec<MetricsSample>, AppError> {
+        let history = self.history.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock metrics history: {}", e)))?;
+
+        Ok(history.iter()
+            .filter(|s| since.is_none_or(|t| s.timestamp >= t))
+            .filter(|s| until.is_none_or(|t| s.timestamp <= t))
+            .cloned()
+            .collect())
+    }
+
+    // CPU usage is relative to the previous process refresh, so the first
+    // reading of a process that is not being tracked is always zero.
+    pub fn process_metrics(&self, target: &ProcessTarget) -> Result<ProcessMetrics, AppError> {
+        target.validate()?;
+
+        let mut sys = self.system.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock system state: {}", e)))?;
+        sys.refresh_processes();
+
+        let pid = target.resolve(&sys)
+            .ok_or_else(|| AppError::not_found("No matching process found"))?;
+        process::tree_metrics(&sys, pid)
+            .ok_or_else(|| AppError::not_found(format!("Process {} exited", pid)))
+    }
+
+    pub fn track_process(&self, target: ProcessTarget) -> Result<ProcessMetrics, AppError> {
+        let metrics = self.process_metrics(&target)?;
+
+        *self.tracked.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock tracked process: {}", e)))? = Some(target);
+        Ok(metrics)
+    }
+
+    pub fn untrack_process(&self) -> Result<(), AppError> {
+        *self.tracked.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock tracked process: {}", e)))? = None;
+        Ok(())
+    }
+
+    pub fn samples_between(&self, start: Instant, end: Instant) -> Result<Vec<MetricsSample>, AppError> {
+        let history = self.history.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock metrics history: {}", e)))?;
+
+        Ok(history.iter()
+            .filter(|s| s.instant >= start && s.instant <= end)
+            .cloned()
+            .collect())
+    }
+
+    pub fn is_sampling(&self) -> bool {
+        self.sampling.load(Ordering::SeqCst) || self.run_holds.load(Ordering::SeqCst) > 0
+    }
+
+    // Keeps sampling on until the guard is dropped, independently of the
+    // user's start/stop and of other runs holding it.
+    pub fn hold_sampling(&self) -> SamplingGuard<'_> {
+        self.run_holds.fetch_add(1, Ordering::SeqCst);
+        SamplingGuard { metrics: self }
+    }
+
+    pub fn clear_history(&self) -> Result<(), AppError> {
+        self.history.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock metrics history: {}", e)))?
+            .clear();
+        Ok(())
+    }
+
+    fn tick(&self) {
+        let tracked = match self.tracked.lock() {
+            Ok(tracked) => tracked.clone(),
+            Err(_) => return,
+        };
+
+        let (metrics, process) = {
+            let Ok(mut sys) = self.system.lock() else {
+                return;
+            };
+            sys.refresh_cpu();
+            sys.refresh_memory();
+            if tracked.is_some() {
+                sys.refresh_processes();
+            }
+
+            if !self.is_sampling() {
+                return;
+            }
+            let Ok(metrics) = self.collect_with(&sys) else {
+                return;
+            };
+            // Targets given by name are re-resolved so a restarted server is picked up
+            let process = tracked.as_ref()
+                .and_then(|target| target.resolve(&sys))
+                .and_then(|pid| process::tree_metrics(&sys, pid));
+            (metrics, process)
+        };
+
+        let capacity = match self.config.lock() {
+            Ok(config) => config.capacity,
+            Err(_) => return,
+        };
+        let Ok(mut history) = self.history.lock() else {
+            return;
+        };
+        while history.len() >= capacity {
+            history.pop_front();
+        }
+        history.push_back(MetricsSample {
+            instant: Instant::now(),
+            timestamp: unix_millis(),
+            metrics,
+            process,
+        });
+    }
+
+    fn interval(&self) -> Duration {
+        let interval = match (self.is_sampling(), self.config.lock()) {
+            (true, Ok(config)) => Duration::from_millis(config.interval_ms),
+            _ => REFRESH_INTERVAL,
+        };
+        interval.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL)
+    }
+}
+
+pub struct SamplingGuard<'a> {
+    metrics: &'a MetricsState,
+}
+
+impl Drop for SamplingGuard<'_> {
+    fn drop(&mut self) {
+        self.metrics.run_holds.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+// Single background thread that refreshes the shared System and, while
+// sampling is enabled, appends snapshots to the bounded history.
+pub fn spawn_sampler(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        let state = app.state::<MetricsState>();
+        std::thread::sleep(state.interval());
+        state.tick();
+    });
+}
+
+pub fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+fn disk_info(disk: &sysinfo::Disk) -> DiskInfo {
+    let total = disk.total_space();
+    let available = disk.available_space();
+    let used = total.saturating_sub(available);
+    let used_percentage = if total > 0 {
+        (used as f64 / total as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    DiskInfo {
+        name: disk.name().to_string_lossy().to_string(),
+        mount_point: disk.mount_point().to_string_lossy().to_string(),
+        file_system: disk.file_system().to_string_lossy().to_string(),
+        kind: match disk.kind() {
+            DiskKind::SSD => "SSD".to_string(),
+            DiskKind::HDD => "HDD".to_string(),
+            DiskKind::Unknown(_) => "Unknown".to_string(),
+        },
+        is_removable: disk.is_removable(),
+        total,
+        available,
+        used,
+        used_percentage,
+    }
+}
+
+pub fn collect(
+    sys: &System,
+    disks: &Disks,
+    battery: Option<BatteryInfo>,
+    thermal_zones: Vec<ThermalZone>,
+) -> SystemMetrics {
+    // Memory metrics (GB)
+    let memory_used = sys.used_memory() as f64 / BYTES_PER_GB;
+    let memory_total = sys.total_memory() as f64 / BYTES_PER_GB;
+    let memory_percentage = (memory_used / memory_total) * 100.0;
+    let memory_available = sys.available_memory() as f64 / BYTES_PER_GB;
+
+    // Swap metrics (GB), growth here during a run skews tokens/sec
+    let swap_used = sys.used_swap() as f64 / BYTES_PER_GB;
+    let swap_total = sys.total_swap() as f64 / BYTES_PER_GB;
+    let swap_percentage = if sys.total_swap() > 0 {
+        (swap_used / swap_total) * 100.0
+    } else {
+        0.0
+    };
+
+    // CPU metrics, relative to the previous refresh of the shared System
+    let cpu_usage = sys.global_cpu_info().cpu_usage() as f64;
+    let cpu_per_core = sys.cpus().iter()
+        .map(|cpu| cpu.cpu_usage() as f64)
+        .collect();
+    let cpu_count = sys.physical_core_count().unwrap_or(1);
+
+    // Load averages are always zero on Windows
+    let load = System::load_average();
+
+    // Disk metrics, one entry per mounted filesystem
+    let disk_usage = disks.list().iter()
+        .map(disk_info)
+        .collect();
+
+    // Battery metrics, None on desktops and non-Linux hosts
+    let (battery_level, battery_time_remaining, battery_state) = match battery {
+        Some(b) => (Some(b.level), b.time_remaining, b.state),
+        None => (None, None, "Unavailable".to_string()),
+    };
+
+    // Temperature is the hottest sensor, which is what throttles first
+    let temperature = thermal_zones.iter()
+        .map(|z| z.temperature)
+        .reduce(f64::max);
+
+    SystemMetrics {
+        memory_used,
+        memory_total,
+        memory_percentage,
+        memory_available,
+        swap_used,
+        swap_total,
+        swap_percentage,
+        cpu_usage,
+        cpu_per_core,
+        cpu_count,
+        load_average: load.one,
+        load_average_5m: load.five,
+        load_average_15m: load.fifteen,
+        battery_level,
+        battery_time_remaining,
+        battery_state,
+        disk_usage,
+        temperature,
+        thermal_zones,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping_runs_keep_sampling_on() {
+        let metrics = MetricsState::new();
+        let a = metrics.hold_sampling();
+        let b = metrics.hold_sampling();
+        drop(a);
+        assert!(metrics.is_sampling());
+        drop(b);
+        assert!(!metrics.is_sampling());
+    }
+
+    #[test]
+    fn runs_leave_user_sampling_alone() {
+        let metrics = MetricsState::new();
+        let run = metrics.hold_sampling();
+        metrics.start_sampling(None, None).unwrap();
+        drop(run);
+        assert!(metrics.is_sampling());
+
+        // Stopping while a run holds sampling only ends the user's request
+        let run = metrics.hold_sampling();
+        metrics.stop_sampling().unwrap();
+        assert!(metrics.status().unwrap().sampling);
+        drop(run);
+        assert!(!metrics.is_sampling());
+    }
+}
@@ -0,0 +1,299 @@
+use crate::error::AppError;
+use crate::files;
+use crate::storage::Storage;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+const MAX_DEPTH: usize = 8;
+// Fingerprints hash the size plus three samples instead of whole files, so a
+// rescan of a large library stays fast. They only flag likely copies; files
+// are confirmed identical by the SHA-256 from `hashing::hash_file`.
+const SAMPLE_BYTES: u64 = 1024 * 1024;
+// safetensors files start with a u64 header length followed by JSON
+const MAX_SAFETENSORS_HEADER: u64 = 100 * 1024 * 1024;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelSource {
+    Ollama,
+    LmStudio,
+    HuggingFace,
+    Custom,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelFormat {
+    Gguf,
+    Safetensors,
+}
+
+pub struct ModelDir {
+    pub path: PathBuf,
+    pub source: ModelSource,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ModelFile {
+    pub path: String,
+    pub source: ModelSource,
+    pub format: ModelFormat,
+    pub size: u64,
+    pub modified: u64,
+    pub fingerprint: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ModelLocation {
+    pub path: String,
+    pub source: ModelSource,
+    pub modified: u64,
+    pub last_seen: u64,
+    pub exists: bool,
+    // Full digest of this copy, once it has been hashed
+    pub sha256: Option<String>,
+}
+
+// One model with every place a copy was found. Copies are grouped by sampled
+// fingerprint and split apart when their full digests disagree; `sha256` is
+// set only once every copy has been hashed to the same digest.
+#[derive(Serialize, Clone)]
+pub struct LibraryModel {
+    pub fingerprint: String,
+    pub sha256: Option<String>,
+    pub name: String,
+    pub format: ModelFormat,
+    pub size: u64,
+    pub last_seen: u64,
+    pub locations: Vec<ModelLocation>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ScanReport {
+    pub scanned_dirs: Vec<String>,
+    pub files_found: usize,
+    pub fingerprinted: usize,
+    // Counted by sampled fingerprint, so copies may still differ
+    pub likely_unique_models: usize,
+    // Bytes taken up by copies beyond the first of each model, by sampled
+    // fingerprint and by full SHA-256. Only files hashed with `hash_file`
+    // can be confirmed.
+    pub likely_duplicate_bytes: u64,
+    pub confirmed_duplicate_bytes: u64,
+    pub errors: Vec<String>,
+}
+
+// Default model locations of Ollama, LM Studio and the Hugging Face cache
+pub fn default_dirs() -> Vec<ModelDir> {
+    let mut dirs = Vec::new();
+    let mut add = |path: PathBuf, source| dirs.push(ModelDir { path, source });
+
+    if let Some(models) = std::env::var_os("OLLAMA_MODELS") {
+        add(PathBuf::from(models), ModelSource::Ollama);
+    }
+    if let Some(home) = dirs::home_dir() {
+        add(home.join(".ollama").join("models"), ModelSource::Ollama);
+        add(home.join(".lmstudio").join("models"), ModelSource::LmStudio);
+        add(home.join(".cache").join("lm-studio").join("models"), ModelSource::LmStudio);
+    }
+    match std::env::var_os("HF_HOME") {
+        Some(hf_home) => add(PathBuf::from(hf_home).join("hub"), ModelSource::HuggingFace),
+        None => {
+            if let Some(cache) = dirs::cache_dir() {
+                add(cache.join("huggingface").join("hub"), ModelSource::HuggingFace);
+            }
+            if let Some(home) = dirs::home_dir() {
+                add(home.join(".cache").join("huggingface").join("hub"), ModelSource::HuggingFace);
+            }
+        }
+    }
+    dirs
+}
+
+pub fn scan(storage: &Storage, dirs: &[ModelDir]) -> Result<ScanReport, AppError> {
+    let scan_time = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut report = ScanReport {
+        scanned_dirs: Vec::new(),
+        files_found: 0,
+        fingerprinted: 0,
+        likely_unique_models: 0,
+        likely_duplicate_bytes: 0,
+        confirmed_duplicate_bytes: 0,
+        errors: Vec::new(),
+    };
+    let mut visited_dirs = HashSet::new();
+    let mut seen_files = HashSet::new();
+    let mut found = Vec::new();
+
+    for dir in dirs {
+        let Ok(root) = dir.path.canonicalize() else {
+            continue;
+        };
+        report.scanned_dirs.push(root.display().to_string());
+        walk(&root, dir.source, 0, &mut visited_dirs, &mut seen_files, &mut found, &mut report.errors);
+    }
+    report.files_found = found.len();
+
+    let mut files = Vec::new();
+    for (path, source, format) in found {
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        let path_str = path.display().to_string();
+        let size = metadata.len();
+        let modified = metadata.modified()
+            .map(|t| t.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs())
+            .unwrap_or(0);
+
+        // Unchanged files keep their stored fingerprint
+        let fingerprint = match storage.model_file_fingerprint(&path_str, size, modified)? {
+            Some(fingerprint) => fingerprint,
+            None => match fingerprint(&path, size) {
+                Ok(fingerprint) => {
+                    report.fingerprinted += 1;
+                    fingerprint
+                }
+                Err(e) => {
+                    report.errors.push(e.message);
+                    continue;
+                }
+            },
+        };
+        files.push(ModelFile { path: path_str, source, format, size, modified, fingerprint });
+    }
+
+    storage.record_model_files(&files, scan_time)?;
+
+    let mut fingerprints = HashSet::new();
+    let mut digests = HashSet::new();
+    for file in &files {
+        if !fingerprints.insert(&file.fingerprint) {
+            report.likely_duplicate_bytes += file.size;
+        }
+        if let Some(sha256) = storage.cached_digest(&file.path, file.size, file.modified)? {
+            if !digests.insert(sha256) {
+                report.confirmed_duplicate_bytes += file.size;
+            }
+        }
+    }
+    report.likely_unique_models = fingerprints.len();
+    Ok(report)
+}
+
+fn walk(
+    dir: &Path,
+    source: ModelSource,
+    depth: usize,
+    visited_dirs: &mut HashSet<PathBuf>,
+    seen_files: &mut HashSet<PathBuf>,
+    found: &mut Vec<(PathBuf, ModelSource, ModelFormat)>,
+    errors: &mut Vec<String>,
+) {
+    if depth > MAX_DEPTH || !visited_dirs.insert(dir.to_path_buf()) {
+        return;
+    }
+
+    let entries = match files::list(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push(format!("{}: {}", dir.display(), e));
+            return;
+        }
+    };
+
+    for entry in entries {
+        // The Hugging Face cache links every snapshot file into `blobs`;
+        // the snapshot links carry the real file names.
+        if source == ModelSource::HuggingFace && entry.is_directory && entry.name == "blobs" {
+            continue;
+        }
+
+        // Symlinks are resolved only to avoid counting a linked file twice;
+        // the index keeps the path the user would recognize.
+        let Ok(target) = Path::new(&entry.path).canonicalize() else {
+            continue;
+        };
+        if target.is_dir() {
+            walk(&target, source, depth + 1, visited_dirs, seen_files, found, errors);
+        } else if !seen_files.contains(&target) {
+            if let Some(format) = detect_format(&target) {
+                seen_files.insert(target);
+                found.push((PathBuf::from(entry.path), source, format));
+            }
+        }
+    }
+}
+
+// Identifies model files by their leading bytes rather than their names;
+// Ollama stores models as extensionless `sha256-...` blobs.
+pub fn detect_format(path: &Path) -> Option<ModelFormat> {
+    let mut header = [0u8; 9];
+    File::open(path).and_then(|mut file| file.read_exact(&mut header)).ok()?;
+
+    if &header[..4] == b"GGUF" {
+        return Some(ModelFormat::Gguf);
+    }
+    let header_len = u64::from_le_bytes(header[..8].try_into().ok()?);
+    if header[8] == b'{' && header_len > 1 && header_len < MAX_SAFETENSORS_HEADER {
+        return Some(ModelFormat::Safetensors);
+    }
+    None
+}
+
+fn fingerprint(path: &Path, size: u64) -> Result<String, AppError> {
+    let mut file = File::open(path)
+        .map_err(|e| AppError::io(&format!("Failed to open {}", path.display()), e))?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(size.to_le_bytes());
+    let mut buf = Vec::with_capacity(SAMPLE_BYTES as usize);
+    for offset in [0, size.saturating_sub(SAMPLE_BYTES) / 2, size.saturating_sub(SAMPLE_BYTES)] {
+        buf.clear();
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| (&mut file).take(SAMPLE_BYTES).read_to_end(&mut buf))
+            .map_err(|e| AppError::io(&format!("Failed to read {}", path.display()), e))?;
+        hasher.update(&buf);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duplicates_are_confirmed_only_by_full_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = tempfile::tempdir().unwrap();
+        let storage = Storage::open_in(data.path()).unwrap();
+        let mut gguf = b"GGUF".to_vec();
+        gguf.resize(4096, 0);
+        for name in ["a.gguf", "b.gguf", "c.gguf"] {
+            std::fs::write(dir.path().join(name), &gguf).unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), "not a model").unwrap();
+        let dirs = [ModelDir { path: dir.path().to_path_buf(), source: ModelSource::Custom }];
+
+        let report = scan(&storage, &dirs).unwrap();
+        assert_eq!(report.files_found, 3);
+        assert_eq!(report.likely_unique_models, 1);
+        assert_eq!(report.likely_duplicate_bytes, 2 * 4096);
+        assert_eq!(report.confirmed_duplicate_bytes, 0);
+
+        let no_cancel = std::sync::atomic::AtomicBool::new(false);
+        for name in ["a.gguf", "b.gguf"] {
+            crate::hashing::hash_file(&storage, &dir.path().canonicalize().unwrap().join(name), &no_cancel, &mut |_, _| {}).unwrap();
+        }
+        let report = scan(&storage, &dirs).unwrap();
+        assert_eq!(report.fingerprinted, 0);
+        assert_eq!(report.confirmed_duplicate_bytes, 4096);
+    }
+}
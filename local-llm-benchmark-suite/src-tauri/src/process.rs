@@ -0,0 +1,106 @@
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use sysinfo::{Pid, Process, System};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProcessTarget {
+    pub pid: Option<u32>,
+    pub name: Option<String>,
+}
+
+// Aggregated over the target process and all of its descendants. CPU usage is
+// summed per-core percentages, so 400.0 means four saturated cores.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProcessMetrics {
+    pub pid: u32,
+    pub name: String,
+    pub process_count: usize,
+    pub cpu_usage: f64,
+    pub memory: u64,
+    pub virtual_memory: u64,
+    pub thread_count: usize,
+    pub disk_read_bytes: u64,
+    pub disk_written_bytes: u64,
+    pub total_disk_read_bytes: u64,
+    pub total_disk_written_bytes: u64,
+}
+
+impl ProcessTarget {
+    pub fn validate(&self) -> Result<(), AppError> {
+        match (&self.pid, &self.name) {
+            (None, None) => Err(AppError::invalid_input("Process target needs a pid or a name")),
+            (None, Some(name)) if name.trim().is_empty() => Err(AppError::invalid_input("Process name must not be empty")),
+            _ => Ok(()),
+        }
+    }
+
+    // A name can match a whole tree (e.g. `ollama serve` and its runners), so
+    // the oldest match whose parent is not itself a match is taken as the root.
+    pub fn resolve(&self, sys: &System) -> Option<Pid> {
+        if let Some(pid) = self.pid {
+            let pid = Pid::from_u32(pid);
+            return sys.process(pid).map(|_| pid);
+        }
+
+        let name = self.name.as_deref()?.to_lowercase();
+        let matches: Vec<&Process> = sys.processes().values()
+            .filter(|p| p.thread_kind().is_none())
+            .filter(|p| p.name().to_lowercase().contains(&name))
+            .collect();
+
+        matches.iter()
+            .filter(|p| p.parent().is_none_or(|parent| !matches.iter().any(|m| m.pid() == parent)))
+            .min_by_key(|p| (p.start_time(), p.pid()))
+            .map(|p| p.pid())
+    }
+}
+
+pub fn tree_metrics(sys: &System, root: Pid) -> Option<ProcessMetrics> {
+    let root_process = sys.process(root)?;
+
+    let mut children: HashMap<Pid, Vec<Pid>> = HashMap::new();
+    for process in sys.processes().values().filter(|p| p.thread_kind().is_none()) {
+        if let Some(parent) = process.parent() {
+            children.entry(parent).or_default().push(process.pid());
+        }
+    }
+
+    let mut metrics = ProcessMetrics {
+        pid: root.as_u32(),
+        name: root_process.name().to_string(),
+        process_count: 0,
+        cpu_usage: 0.0,
+        memory: 0,
+        virtual_memory: 0,
+        thread_count: 0,
+        disk_read_bytes: 0,
+        disk_written_bytes: 0,
+        total_disk_read_bytes: 0,
+        total_disk_written_bytes: 0,
+    };
+
+    let mut pending = vec![root];
+    while let Some(pid) = pending.pop() {
+        let Some(process) = sys.process(pid) else {
+            continue;
+        };
+        let disk = process.disk_usage();
+
+        metrics.process_count += 1;
+        metrics.cpu_usage += process.cpu_usage() as f64;
+        metrics.memory += process.memory();
+        metrics.virtual_memory += process.virtual_memory();
+        metrics.thread_count += process.tasks().map_or(1, |tasks| tasks.len().max(1));
+        metrics.disk_read_bytes += disk.read_bytes;
+        metrics.disk_written_bytes += disk.written_bytes;
+        metrics.total_disk_read_bytes += disk.total_read_bytes;
+        metrics.total_disk_written_bytes += disk.total_written_bytes;
+
+        if let Some(kids) = children.get(&pid) {
+            pending.extend(kids);
+        }
+    }
+
+    Some(metrics)
+}
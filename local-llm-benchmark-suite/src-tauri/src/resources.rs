@@ -0,0 +1,78 @@
+use crate::metrics::{MetricsSample, SystemMetrics};
+use serde::{Deserialize, Serialize};
+
+// A core counts as busy when it is at or above this utilization.
+const BUSY_CORE_THRESHOLD: f64 = 90.0;
+
+// Resource usage of a benchmark run, relative to an idle snapshot taken just
+// before it started. Memory figures are GB, CPU figures are percentages.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ResourceUsage {
+    pub sample_count: usize,
+    pub baseline_memory_used: f64,
+    pub peak_memory_used: f64,
+    pub mean_memory_used: f64,
+    pub peak_memory_delta: f64,
+    pub mean_memory_delta: f64,
+    pub baseline_swap_used: f64,
+    pub peak_swap_delta: f64,
+    pub baseline_cpu_usage: f64,
+    pub peak_cpu_usage: f64,
+    pub mean_cpu_usage: f64,
+    pub peak_busy_cores: usize,
+    pub mean_busy_cores: f64,
+    pub baseline_temperature: Option<f64>,
+    pub peak_temperature: Option<f64>,
+    pub peak_temperature_delta: Option<f64>,
+    pub process_peak_memory: Option<u64>,
+    pub process_mean_cpu_usage: Option<f64>,
+}
+
+pub fn summarize(baseline: &SystemMetrics, samples: &[MetricsSample]) -> ResourceUsage {
+    let mut usage = ResourceUsage {
+        sample_count: samples.len(),
+        baseline_memory_used: baseline.memory_used,
+        baseline_swap_used: baseline.swap_used,
+        baseline_cpu_usage: baseline.cpu_usage,
+        baseline_temperature: baseline.temperature,
+        ..Default::default()
+    };
+    if samples.is_empty() {
+        return usage;
+    }
+
+    let count = samples.len() as f64;
+    let busy_cores = |m: &SystemMetrics| {
+        m.cpu_per_core.iter().filter(|&&c| c >= BUSY_CORE_THRESHOLD).count()
+    };
+
+    usage.peak_memory_used = samples.iter().map(|s| s.metrics.memory_used).fold(f64::MIN, f64::max);
+    usage.mean_memory_used = samples.iter().map(|s| s.metrics.memory_used).sum::<f64>() / count;
+    usage.peak_memory_delta = usage.peak_memory_used - baseline.memory_used;
+    usage.mean_memory_delta = usage.mean_memory_used - baseline.memory_used;
+    usage.peak_swap_delta = samples.iter()
+        .map(|s| s.metrics.swap_used - baseline.swap_used)
+        .fold(f64::MIN, f64::max);
+
+    usage.peak_cpu_usage = samples.iter().map(|s| s.metrics.cpu_usage).fold(f64::MIN, f64::max);
+    usage.mean_cpu_usage = samples.iter().map(|s| s.metrics.cpu_usage).sum::<f64>() / count;
+    usage.peak_busy_cores = samples.iter().map(|s| busy_cores(&s.metrics)).max().unwrap_or(0);
+    usage.mean_busy_cores = samples.iter().map(|s| busy_cores(&s.metrics) as f64).sum::<f64>() / count;
+
+    usage.peak_temperature = samples.iter()
+        .filter_map(|s| s.metrics.temperature)
+        .reduce(f64::max);
+    usage.peak_temperature_delta = usage.peak_temperature
+        .zip(baseline.temperature)
+        .map(|(peak, base)| peak - base);
+
+    let processes: Vec<_> = samples.iter().filter_map(|s| s.process.as_ref()).collect();
+    if !processes.is_empty() {
+        usage.process_peak_memory = processes.iter().map(|p| p.memory).max();
+        usage.process_mean_cpu_usage = Some(
+            processes.iter().map(|p| p.cpu_usage).sum::<f64>() / processes.len() as f64,
+        );
+    }
+
+    usage
+}
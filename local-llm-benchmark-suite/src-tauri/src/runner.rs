@@ -0,0 +1,256 @@
+use crate::backend::BackendConfig;
+use crate::benchmark::{self, BenchmarkProgress, BenchmarkRequest, BenchmarkResult};
+use crate::error::AppError;
+use crate::metrics::MetricsState;
+use crate::server::ServerConfig;
+use crate::stats::{self, Summary};
+use crate::suite::{BenchmarkSuite, PromptCase};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const DEFAULT_WARMUP: u32 = 1;
+
+type MetricExtractor = fn(&BenchmarkResult) -> Option<f64>;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CaseRequest {
+    pub backend: BackendConfig,
+    pub model: String,
+    pub case: PromptCase,
+    #[serde(default)]
+    pub warmup: Option<u32>,
+    #[serde(default)]
+    pub repetitions: Option<u32>,
+    #[serde(default)]
+    pub model_path: Option<String>,
+    #[serde(default)]
+    pub server: Option<ServerConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SuiteRunRequest {
+    pub backend: BackendConfig,
+    pub model: String,
+    pub suite: BenchmarkSuite,
+    #[serde(default)]
+    pub warmup: Option<u32>,
+    #[serde(default)]
+    pub model_path: Option<String>,
+    // One server serves every case of the suite
+    #[serde(default)]
+    pub server: Option<ServerConfig>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CaseResult {
+    pub case: String,
+    pub model: String,
+    pub warmup_runs: u32,
+    pub runs: Vec<BenchmarkResult>,
+    pub failures: Vec<AppError>,
+    pub expected_pass_rate: Option<f64>,
+    pub summary: BTreeMap<String, Summary>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SuiteResult {
+    pub suite: String,
+    pub model: String,
+    pub cases: Vec<CaseResult>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CaseProgress {
+    pub case: String,
+    pub run_id: String,
+    pub iteration: u32,
+    pub total: u32,
+    pub warmup: bool,
+}
+
+pub fn run_case(
+    metrics: &MetricsState,
+    request: &CaseRequest,
+    on_case: &mut dyn FnMut(CaseProgress),
+    on_progress: &mut dyn FnMut(BenchmarkProgress),
+) -> Result<CaseResult, AppError> {
+    let case = &request.case;
+    let warmup = request.warmup.unwrap_or(DEFAULT_WARMUP);
+    let repetitions = request.repetitions.unwrap_or(case.repetitions);
+    if repetitions == 0 {
+        return Err(AppError::invalid_input(format!("Case '{}' needs at least one repetition", case.name)));
+    }
+
+    let mut result = CaseResult {
+        case: case.name.clone(),
+        model: request.model.clone(),
+        warmup_runs: warmup,
+        runs: Vec::new(),
+        failures: Vec::new(),
+        expected_pass_rate: None,
+        summary: BTreeMap::new(),
+    };
+
+    // Warm-up runs load the model and fill caches; their numbers are discarded.
+    for iteration in 1..=warmup {
+        let run = run_request(request);
+        on_case(CaseProgress {
+            case: case.name.clone(),
+            run_id: run.run_id.clone().unwrap_or_default(),
+            iteration,
+            total: warmup,
+            warmup: true,
+        });
+        if let Err(e) = benchmark::run(&run, on_progress) {
+            return Err(AppError {
+                message: format!("Warm-up for case '{}' failed: {}", case.name, e),
+                ..e
+            });
+        }
+    }
+
+    for iteration in 1..=repetitions {
+        let run = run_request(request);
+        on_case(CaseProgress {
+            case: case.name.clone(),
+            run_id: run.run_id.clone().unwrap_or_default(),
+            iteration,
+            total: repetitions,
+            warmup: false,
+        });
+        match benchmark::run_monitored(metrics, &run, on_progress) {
+            Ok(run) => result.runs.push(run),
+            Err(e) => result.failures.push(e),
+        }
+    }
+
+    if case.expected.is_some() || !case.expected_contains.is_empty() {
+        let passed = result.runs.iter().filter(|r| matches_expected(case, &r.output)).count();
+        if !result.runs.is_empty() {
+            result.expected_pass_rate = Some(passed as f64 / result.runs.len() as f64);
+        }
+    }
+    result.summary = summarize_runs(&result.runs);
+    Ok(result)
+}
+
+pub fn run_suite(
+    metrics: &MetricsState,
+    request: &SuiteRunRequest,
+    on_case: &mut dyn FnMut(CaseProgress),
+    on_progress: &mut dyn FnMut(BenchmarkProgress),
+) -> Result<SuiteResult, AppError> {
+    let mut cases = Vec::new();
+    for case in &request.suite.prompts {
+        let case_request = CaseRequest {
+            backend: request.backend.clone(),
+            model: request.model.clone(),
+            case: case.clone(),
+            warmup: request.warmup,
+            repetitions: None,
+            model_path: request.model_path.clone(),
+            server: None,
+        };
+        // A case that cannot run is recorded as failed; the finished cases
+        // are still worth keeping
+        let result = run_case(metrics, &case_request, on_case, on_progress).unwrap_or_else(|e| CaseResult {
+            case: case.name.clone(),
+            model: request.model.clone(),
+            warmup_runs: case_request.warmup.unwrap_or(DEFAULT_WARMUP),
+            runs: Vec::new(),
+            failures: vec![e],
+            expected_pass_rate: None,
+            summary: BTreeMap::new(),
+        });
+        cases.push(result);
+    }
+
+    Ok(SuiteResult {
+        suite: request.suite.name.clone(),
+        model: request.model.clone(),
+        cases,
+    })
+}
+
+fn run_request(request: &CaseRequest) -> BenchmarkRequest {
+    BenchmarkRequest {
+        backend: request.backend.clone(),
+        model: request.model.clone(),
+        prompt: request.case.prompt.clone(),
+        system: request.case.system.clone(),
+        options: request.case.sampling.clone(),
+        run_id: Some(benchmark::new_run_id()),
+        model_path: request.model_path.clone(),
+        // The caller owns the server for the whole case
+        server: None,
+    }
+}
+
+fn matches_expected(case: &PromptCase, output: &str) -> bool {
+    let output = output.trim().to_lowercase();
+    let exact = case.expected.as_ref()
+        .is_none_or(|expected| output == expected.trim().to_lowercase());
+    let contains = case.expected_contains.iter()
+        .all(|needle| output.contains(&needle.to_lowercase()));
+    exact && contains
+}
+
+fn summarize_runs(runs: &[BenchmarkResult]) -> BTreeMap<String, Summary> {
+    let metrics: [(&str, MetricExtractor); 7] = [
+        ("time_to_first_token_ms", |r| r.time_to_first_token_ms),
+        ("total_latency_ms", |r| Some(r.total_latency_ms)),
+        ("inter_token_latency_ms", |r| (r.inter_token_latency.count > 0).then_some(r.inter_token_latency.mean_ms)),
+        ("prompt_tokens_per_second", |r| r.prompt_tokens_per_second),
+        ("generation_tokens_per_second", |r| r.generation_tokens_per_second),
+        ("completion_tokens", |r| r.completion_tokens.map(|t| t as f64)),
+        ("output_chars", |r| Some(r.output_chars as f64)),
+    ];
+
+    metrics.iter()
+        .filter_map(|(name, extract)| {
+            let values: Vec<f64> = runs.iter().filter_map(extract).collect();
+            stats::summarize(&values).map(|summary| (name.to_string(), summary))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{stub, BackendKind};
+    use serde_json::json;
+
+    #[test]
+    fn failed_warmup_does_not_abort_the_suite() {
+        let done = || stub::ndjson(&[
+            json!({"model": "llama3", "message": {"role": "assistant", "content": "ok"}, "done": false}),
+            json!({"model": "llama3", "done": true, "eval_count": 1, "eval_duration": 1_000_000}),
+        ]);
+        let server = stub::serve(vec![
+            stub::status(500, json!({"error": "out of memory"}).to_string()),
+            done(),
+            done(),
+        ]);
+        let suite = crate::suite::validate(
+            "version = 1\nname = \"smoke\"\n[[prompts]]\nname = \"first\"\nprompt = \"a\"\n[[prompts]]\nname = \"second\"\nprompt = \"b\"\n",
+        )
+        .suite
+        .unwrap();
+        let request = SuiteRunRequest {
+            backend: BackendConfig { kind: BackendKind::Ollama, base_url: Some(server.base_url.clone()), api_key: None },
+            model: "llama3".to_string(),
+            suite,
+            warmup: Some(1),
+            model_path: None,
+            server: None,
+        };
+
+        let result = run_suite(&MetricsState::new(), &request, &mut |_| {}, &mut |_| {}).unwrap();
+        assert_eq!(result.cases.len(), 2);
+        assert_eq!(result.cases[0].case, "first");
+        assert!(result.cases[0].runs.is_empty());
+        assert!(result.cases[0].failures[0].message.contains("Warm-up for case 'first' failed"));
+        assert_eq!(result.cases[1].runs.len(), 1);
+        assert!(result.cases[1].failures.is_empty());
+    }
+}
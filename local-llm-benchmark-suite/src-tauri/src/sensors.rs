@@ -0,0 +1,237 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use sysinfo::Components;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ThermalZone {
+    pub name: String,
+    pub source: String,
+    pub temperature: f64,
+    pub critical: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BatteryInfo {
+    pub level: f64,
+    pub state: String,
+    pub time_remaining: Option<String>,
+}
+
+// Reads Linux sysfs sensors relative to `root`, which is "/" in the app and a
+// fake sysfs tree when exercising the parsers.
+pub struct SensorReader {
+    root: PathBuf,
+}
+
+impl SensorReader {
+    pub fn new() -> Self {
+        Self::with_root("/")
+    }
+
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn battery(&self) -> Option<BatteryInfo> {
+        let mut supplies: Vec<PathBuf> = std::fs::read_dir(self.root.join("sys/class/power_supply"))
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| read_trimmed(&path.join("type")).as_deref() == Some("Battery"))
+            .collect();
+        supplies.sort();
+
+        supplies.iter().find_map(|path| read_battery(path))
+    }
+
+    pub fn thermal_zones(&self) -> Vec<ThermalZone> {
+        let Ok(entries) = std::fs::read_dir(self.root.join("sys/class/thermal")) else {
+            return Vec::new();
+        };
+
+        let mut zones: Vec<(String, ThermalZone)> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("thermal_zone"))
+            .filter_map(|entry| {
+                let dir = entry.file_name().to_string_lossy().to_string();
+                read_thermal_zone(&entry.path(), &dir).map(|zone| (dir, zone))
+            })
+            .collect();
+        zones.sort_by_key(|(dir, _)| natural_zone_order(dir));
+
+        zones.into_iter().map(|(_, zone)| zone).collect()
+    }
+}
+
+pub fn component_zones(components: &Components) -> Vec<ThermalZone> {
+    components.list().iter()
+        .filter(|c| c.temperature().is_finite() && c.temperature() > 0.0)
+        .map(|c| ThermalZone {
+            name: c.label().to_string(),
+            source: "component".to_string(),
+            temperature: c.temperature() as f64,
+            critical: c.critical().map(|t| t as f64),
+        })
+        .collect()
+}
+
+fn read_trimmed(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn read_number(path: &Path) -> Option<f64> {
+    read_trimmed(path)?.parse().ok()
+}
+
+fn read_battery(dir: &Path) -> Option<BatteryInfo> {
+    let state = read_trimmed(&dir.join("status")).unwrap_or_else(|| "Unknown".to_string());
+
+    // Batteries report either energy (µWh/µW) or charge (µAh/µA) counters.
+    let (now, full, rate) = match read_number(&dir.join("energy_now")) {
+        Some(now) => (
+            Some(now),
+            read_number(&dir.join("energy_full")),
+            read_number(&dir.join("power_now")),
+        ),
+        None => (
+            read_number(&dir.join("charge_now")),
+            read_number(&dir.join("charge_full")),
+            read_number(&dir.join("current_now")),
+        ),
+    };
+
+    let level = read_number(&dir.join("capacity")).or_else(|| match (now, full) {
+        (Some(now), Some(full)) if full > 0.0 => Some((now / full * 100.0).min(100.0)),
+        _ => None,
+    })?;
+
+    let hours = match (state.as_str(), now, full, rate.map(f64::abs)) {
+        ("Discharging", Some(now), _, Some(rate)) if rate > 0.0 => Some(now / rate),
+        ("Charging", Some(now), Some(full), Some(rate)) if rate > 0.0 => Some((full - now).max(0.0) / rate),
+        _ => None,
+    };
+
+    Some(BatteryInfo {
+        level,
+        state,
+        time_remaining: hours.map(format_hours),
+    })
+}
+
+fn read_thermal_zone(dir: &Path, dir_name: &str) -> Option<ThermalZone> {
+    // sysfs reports millidegrees Celsius
+    let temperature = read_number(&dir.join("temp"))? / 1000.0;
+    let name = read_trimmed(&dir.join("type")).unwrap_or_else(|| dir_name.to_string());
+
+    let critical = (0..)
+        .map_while(|i| {
+            read_trimmed(&dir.join(format!("trip_point_{}_type", i)))
+                .map(|kind| (i, kind))
+        })
+        .find(|(_, kind)| kind == "critical")
+        .and_then(|(i, _)| read_number(&dir.join(format!("trip_point_{}_temp", i))))
+        .map(|t| t / 1000.0);
+
+    Some(ThermalZone {
+        name,
+        source: dir_name.to_string(),
+        temperature,
+        critical,
+    })
+}
+
+fn natural_zone_order(dir_name: &str) -> u32 {
+    dir_name.trim_start_matches("thermal_zone").parse().unwrap_or(u32::MAX)
+}
+
+fn format_hours(hours: f64) -> String {
+    let minutes = (hours * 60.0).round() as u64;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    fn battery(files: &[(&str, &str)]) -> BatteryInfo {
+        let root = tempfile::tempdir().unwrap();
+        write(root.path(), "sys/class/power_supply/AC/type", "Mains\n");
+        write(root.path(), "sys/class/power_supply/BAT0/type", "Battery\n");
+        for (name, contents) in files {
+            write(root.path(), &format!("sys/class/power_supply/BAT0/{}", name), contents);
+        }
+        SensorReader::with_root(root.path()).battery().unwrap()
+    }
+
+    #[test]
+    fn energy_battery_discharging() {
+        let info = battery(&[
+            ("status", "Discharging\n"),
+            ("energy_now", "30000000\n"),
+            ("energy_full", "60000000\n"),
+            ("power_now", "20000000\n"),
+        ]);
+        assert_eq!(info.level, 50.0);
+        assert_eq!(info.state, "Discharging");
+        assert_eq!(info.time_remaining.as_deref(), Some("1h 30m"));
+    }
+
+    #[test]
+    fn charge_battery_charging() {
+        let info = battery(&[
+            ("status", "Charging\n"),
+            ("charge_now", "1000000\n"),
+            ("charge_full", "4000000\n"),
+            ("current_now", "2000000\n"),
+            ("capacity", "25\n"),
+        ]);
+        assert_eq!(info.level, 25.0);
+        assert_eq!(info.time_remaining.as_deref(), Some("1h 30m"));
+    }
+
+    #[test]
+    fn full_battery_has_no_estimate() {
+        let info = battery(&[("status", "Full\n"), ("capacity", "100\n"), ("charge_now", "4000000\n")]);
+        assert_eq!(info.level, 100.0);
+        assert!(info.time_remaining.is_none());
+    }
+
+    #[test]
+    fn no_battery() {
+        let root = tempfile::tempdir().unwrap();
+        write(root.path(), "sys/class/power_supply/AC/type", "Mains\n");
+        assert!(SensorReader::with_root(root.path()).battery().is_none());
+    }
+
+    #[test]
+    fn thermal_zones_sorted_naturally_with_critical_trip() {
+        let root = tempfile::tempdir().unwrap();
+        for (zone, temp) in [("thermal_zone10", "55000"), ("thermal_zone2", "45500"), ("thermal_zone0", "40000")] {
+            write(root.path(), &format!("sys/class/thermal/{}/temp", zone), temp);
+        }
+        write(root.path(), "sys/class/thermal/thermal_zone2/type", "x86_pkg_temp\n");
+        write(root.path(), "sys/class/thermal/thermal_zone2/trip_point_0_type", "passive\n");
+        write(root.path(), "sys/class/thermal/thermal_zone2/trip_point_0_temp", "90000\n");
+        write(root.path(), "sys/class/thermal/thermal_zone2/trip_point_1_type", "critical\n");
+        write(root.path(), "sys/class/thermal/thermal_zone2/trip_point_1_temp", "105000\n");
+        write(root.path(), "sys/class/thermal/cooling_device0/type", "Fan\n");
+
+        let zones = SensorReader::with_root(root.path()).thermal_zones();
+        let sources: Vec<&str> = zones.iter().map(|z| z.source.as_str()).collect();
+        assert_eq!(sources, ["thermal_zone0", "thermal_zone2", "thermal_zone10"]);
+
+        assert_eq!(zones[1].name, "x86_pkg_temp");
+        assert_eq!(zones[1].temperature, 45.5);
+        assert_eq!(zones[1].critical, Some(105.0));
+        assert_eq!(zones[0].name, "thermal_zone0");
+        assert_eq!(zones[0].critical, None);
+    }
+}
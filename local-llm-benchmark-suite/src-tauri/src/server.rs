@@ -0,0 +1,774 @@
+use crate::error::{AppError, ErrorCode};
+use crate::fs_scope::{self, FsScope};
+use crate::metrics::unix_millis;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Read};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_BINARY: &str = "llama-server";
+// Lists the binaries the webview may launch; kept in the backend-only config
+// directory next to allowed_roots.json
+const BINARIES_FILE: &str = "servers.json";
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_HEALTH_PATH: &str = "/health";
+// Large models can take minutes to load from disk
+const DEFAULT_STARTUP_TIMEOUT_SECS: u64 = 300;
+const HEALTH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+const HEALTH_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_LOG_LINES: usize = 2000;
+// Lines included in the error when a server fails to come up
+const ERROR_LOG_LINES: usize = 20;
+// How long to wait for the log readers to drain output after the process died
+const DRAIN_TIMEOUT: Duration = Duration::from_millis(500);
+// Device selection only; anything else (LD_PRELOAD, PATH, ...) could change
+// what the binary runs
+const ALLOWED_ENV: &[&str] = &[
+    "CUDA_VISIBLE_DEVICES",
+    "HIP_VISIBLE_DEVICES",
+    "ROCR_VISIBLE_DEVICES",
+    "GGML_VK_VISIBLE_DEVICES",
+    "ONEAPI_DEVICE_SELECTOR",
+    "OMP_NUM_THREADS",
+];
+// llama-server flags that take a file to read, and ones that write a file.
+// Their values go through the filesystem scope; paths anywhere else in the
+// arguments are rejected.
+const READ_PATH_FLAGS: &[&str] = &[
+    "-m", "--model", "-md", "--model-draft", "--lora", "--lora-scaled",
+    "--control-vector", "--control-vector-scaled", "--mmproj", "--grammar-file",
+    "-jf", "--json-schema-file", "--chat-template-file", "-f", "--file",
+    "--system-prompt-file", "--api-key-file", "--ssl-key-file", "--ssl-cert-file", "--path",
+];
+const WRITE_PATH_FLAGS: &[&str] = &["--log-file", "--slot-save-path", "--prompt-cache"];
+
+static SERVER_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Arguments may use the placeholders {model}, {host} and {port}. When they
+// don't mention the model or port, llama-server's `--model`, `--host` and
+// `--port` flags are appended, so `args` only needs the settings under test
+// (e.g. ["--threads", "8", "--ctx-size", "16384"]).
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ServerConfig {
+    // Name of a binary listed in servers.json; llama-server on PATH if unset
+    #[serde(default)]
+    pub binary: Option<String>,
+    #[serde(default)]
+    pub model_path: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub host: Option<String>,
+    // A free port is picked when unset
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub health_path: Option<String>,
+    #[serde(default)]
+    pub startup_timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ServerBinary {
+    pub name: String,
+    pub path: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ServerBinaryList {
+    pub binaries: Vec<ServerBinary>,
+    pub allow_remote_host: bool,
+    pub config_file: String,
+    pub config_error: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+struct BinariesConfig {
+    #[serde(default)]
+    binaries: HashMap<String, PathBuf>,
+    // Lets `host` bind beyond loopback, exposing the server to the network
+    #[serde(default)]
+    allow_remote_host: bool,
+}
+
+// Server binaries the webview may launch, by name. Only the backend-only
+// config directory can add one, so the webview cannot run a file it wrote.
+pub struct ServerBinaries {
+    binaries: HashMap<String, PathBuf>,
+    allow_remote_host: bool,
+    config_file: PathBuf,
+    config_error: Option<String>,
+}
+
+impl ServerBinaries {
+    pub fn load(data_dir: &Path) -> Self {
+        let config_file = fs_scope::config_dir(data_dir).join(BINARIES_FILE);
+        let (config, config_error) = match read_binaries(&config_file) {
+            Ok(config) => (config, None),
+            Err(e) => (BinariesConfig::default(), Some(e)),
+        };
+        Self {
+            binaries: config.binaries,
+            allow_remote_host: config.allow_remote_host,
+            config_file,
+            config_error,
+        }
+    }
+
+    // Servers listen on loopback only, unless servers.json allows otherwise
+    pub fn check_host(&self, host: Option<&str>) -> Result<(), AppError> {
+        let Some(host) = host else {
+            return Ok(());
+        };
+        let loopback = host.eq_ignore_ascii_case("localhost")
+            || host.trim_start_matches('[').trim_end_matches(']')
+                .parse::<std::net::IpAddr>()
+                .is_ok_and(|ip| ip.is_loopback());
+        if loopback || self.allow_remote_host {
+            return Ok(());
+        }
+        Err(AppError::new(
+            ErrorCode::PermissionDenied,
+            format!(
+                "Refusing to bind a server to {}: only loopback hosts are allowed unless allow_remote_host is set in {}",
+                host,
+                self.config_file.display()
+            ),
+        ))
+    }
+
+    pub fn list(&self) -> ServerBinaryList {
+        let mut binaries: Vec<ServerBinary> = self.binaries.iter()
+            .map(|(name, path)| ServerBinary { name: name.clone(), path: path.display().to_string() })
+            .collect();
+        binaries.sort_by(|a, b| a.name.cmp(&b.name));
+        ServerBinaryList {
+            binaries,
+            allow_remote_host: self.allow_remote_host,
+            config_file: self.config_file.display().to_string(),
+            config_error: self.config_error.clone(),
+        }
+    }
+
+    // Resolves a configured binary, or llama-server (configured or on PATH)
+    // when no name is given. Files the webview could overwrite are refused
+    // wherever they were found.
+    pub fn resolve(&self, name: Option<&str>, scope: &FsScope) -> Result<PathBuf, AppError> {
+        let path = match (name, self.binaries.get(name.unwrap_or(DEFAULT_BINARY))) {
+            (_, Some(path)) => path.clone(),
+            (None, None) => find_on_path(DEFAULT_BINARY)
+                .ok_or_else(|| AppError::not_found(format!("{} was not found on PATH", DEFAULT_BINARY)))?,
+            (Some(name), None) => {
+                return Err(AppError::not_found(format!(
+                    "No server binary named {} in {}",
+                    name,
+                    self.config_file.display()
+                )));
+            }
+        };
+
+        let resolved = path.canonicalize()
+            .map_err(|e| AppError::io(&format!("Failed to resolve {}", path.display()), e))?;
+        if scope.is_writable(&resolved) {
+            return Err(AppError::new(
+                ErrorCode::PermissionDenied,
+                format!("Refusing to launch {}: it is inside an allowed root", resolved.display()),
+            ));
+        }
+        Ok(resolved)
+    }
+}
+
+// Checks the parts of a launch request the webview controls: environment
+// variables must be allowlisted, and file paths in the arguments must resolve
+// inside the scope. Path flags get the resolved path in place of the value.
+pub fn scoped_config(config: &ServerConfig, scope: &FsScope) -> Result<ServerConfig, AppError> {
+    if let Some(name) = config.env.keys().find(|name| !ALLOWED_ENV.contains(&name.as_str())) {
+        return Err(AppError::invalid_input(format!(
+            "Environment variable {} is not allowed; allowed are {}",
+            name,
+            ALLOWED_ENV.join(", ")
+        )));
+    }
+
+    let mut args = Vec::new();
+    let mut iter = config.args.iter();
+    while let Some(arg) = iter.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with('-') => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        // The bind address goes through `host`, which is checked separately
+        if flag == "--host" {
+            return Err(AppError::invalid_input("Set the server's host with `host` rather than --host"));
+        }
+        let read = READ_PATH_FLAGS.contains(&flag);
+        if !read && !WRITE_PATH_FLAGS.contains(&flag) {
+            if looks_like_path(arg) {
+                return Err(AppError::invalid_input(format!(
+                    "Argument {} looks like a path; paths are only accepted after a known file flag",
+                    arg
+                )));
+            }
+            args.push(arg.clone());
+            continue;
+        }
+
+        let value = match inline {
+            Some(value) => value,
+            None => iter.next()
+                .cloned()
+                .ok_or_else(|| AppError::invalid_input(format!("{} needs a path", flag)))?,
+        };
+        // {model} expands to `model_path`, which is resolved separately
+        if read && value == "{model}" {
+            args.extend([flag.to_string(), value]);
+            continue;
+        }
+        let resolved = match read {
+            true => scope.resolve(&value)?,
+            false => scope.resolve_for_write(&value)?,
+        };
+        args.extend([flag.to_string(), resolved.display().to_string()]);
+    }
+
+    Ok(ServerConfig { args, ..config.clone() })
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ServerStatus {
+    Starting,
+    Ready,
+    Exited,
+}
+
+#[derive(Serialize, Clone)]
+pub struct ServerInfo {
+    pub id: String,
+    pub pid: u32,
+    pub binary: String,
+    pub args: Vec<String>,
+    pub model_path: Option<String>,
+    pub base_url: String,
+    pub started_at: u64,
+    pub status: ServerStatus,
+    pub exit_code: Option<i32>,
+}
+
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Serialize, Clone)]
+pub struct LogLine {
+    pub timestamp: u64,
+    pub stream: LogStream,
+    pub line: String,
+}
+
+type ServerLog = Arc<Mutex<VecDeque<LogLine>>>;
+
+struct ManagedServer {
+    info: ServerInfo,
+    child: Child,
+    log: ServerLog,
+    readers: Vec<JoinHandle<()>>,
+}
+
+impl ManagedServer {
+    // Refreshes `info.status` if the process has exited on its own
+    fn poll(&mut self) -> &ServerInfo {
+        if self.info.status != ServerStatus::Exited {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                self.info.status = ServerStatus::Exited;
+                self.info.exit_code = status.code();
+            }
+        }
+        &self.info
+    }
+
+    fn kill(&mut self) {
+        if self.poll().status != ServerStatus::Exited {
+            let _ = self.child.kill();
+            if let Ok(status) = self.child.wait() {
+                self.info.exit_code = status.code();
+            }
+            self.info.status = ServerStatus::Exited;
+        }
+    }
+}
+
+impl Drop for ManagedServer {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+#[derive(Default)]
+pub struct ServerManager {
+    servers: Mutex<HashMap<String, ManagedServer>>,
+}
+
+impl ServerManager {
+    // Launches the server and blocks until its health endpoint answers 200.
+    // `binary`, `model_path` and `config` must already be checked by the
+    // caller with `ServerBinaries::resolve` and `scoped_config`.
+    pub fn start(
+        &self,
+        config: &ServerConfig,
+        binary: &Path,
+        model_path: Option<&Path>,
+    ) -> Result<ServerInfo, AppError> {
+        let host = config.host.clone().unwrap_or_else(|| DEFAULT_HOST.to_string());
+        let port = match config.port {
+            Some(port) => port,
+            None => free_port(&host)?,
+        };
+        let args = expand_args(&config.args, model_path, &host, port);
+
+        let mut child = Command::new(binary)
+            .args(&args)
+            .envs(&config.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| AppError::io(&format!("Failed to launch {}", binary.display()), e))?;
+
+        let log: ServerLog = Arc::new(Mutex::new(VecDeque::new()));
+        let mut readers = Vec::new();
+        if let Some(stdout) = child.stdout.take() {
+            readers.push(capture(stdout, LogStream::Stdout, log.clone()));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            readers.push(capture(stderr, LogStream::Stderr, log.clone()));
+        }
+
+        let id = format!("server-{}-{}", unix_millis(), SERVER_COUNTER.fetch_add(1, Ordering::Relaxed));
+        let info = ServerInfo {
+            id: id.clone(),
+            pid: child.id(),
+            binary: binary.display().to_string(),
+            args,
+            model_path: model_path.map(|p| p.display().to_string()),
+            base_url: format!("http://{}:{}", host, port),
+            started_at: unix_millis(),
+            status: ServerStatus::Starting,
+            exit_code: None,
+        };
+        self.lock()?.insert(id.clone(), ManagedServer { info, child, log, readers });
+
+        let health_path = config.health_path.as_deref().unwrap_or(DEFAULT_HEALTH_PATH);
+        let timeout = Duration::from_secs(config.startup_timeout_secs.unwrap_or(DEFAULT_STARTUP_TIMEOUT_SECS));
+        match self.wait_healthy(&id, health_path, timeout) {
+            Ok(info) => Ok(info),
+            Err(e) => {
+                let Some(mut server) = self.lock()?.remove(&id) else {
+                    return Err(e);
+                };
+                server.kill();
+                // The last lines usually say why the server failed
+                let drain_start = Instant::now();
+                while server.readers.iter().any(|r| !r.is_finished()) && drain_start.elapsed() < DRAIN_TIMEOUT {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                let log = server.log.lock()
+                    .map_err(|e| AppError::internal(format!("Failed to lock server log: {}", e)))?;
+                let skip = log.len().saturating_sub(ERROR_LOG_LINES);
+                let tail: Vec<&str> = log.iter().skip(skip).map(|l| l.line.as_str()).collect();
+                Err(e.with_details(tail.join("\n")))
+            }
+        }
+    }
+
+    fn wait_healthy(&self, id: &str, health_path: &str, timeout: Duration) -> Result<ServerInfo, AppError> {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(HEALTH_TIMEOUT)
+            .timeout(HEALTH_TIMEOUT)
+            .build();
+        let start = Instant::now();
+
+        loop {
+            let info = match self.lock()?.get_mut(id) {
+                Some(server) => server.poll().clone(),
+                None => return Err(AppError::cancelled(format!("Server {} was stopped while starting", id))),
+            };
+            if info.status == ServerStatus::Exited {
+                let code = info.exit_code.map_or("a signal".to_string(), |c| format!("code {}", c));
+                return Err(AppError::new(
+                    ErrorCode::BackendUnavailable,
+                    format!("{} exited with {} before becoming healthy", info.binary, code),
+                ));
+            }
+
+            // llama-server answers 503 until the model is loaded
+            let url = crate::backend::join_url(&info.base_url, health_path);
+            if agent.get(&url).call().is_ok() {
+                let mut servers = self.lock()?;
+                let server = servers.get_mut(id)
+                    .ok_or_else(|| AppError::cancelled(format!("Server {} was stopped while starting", id)))?;
+                server.info.status = ServerStatus::Ready;
+                return Ok(server.info.clone());
+            }
+
+            if start.elapsed() >= timeout {
+                return Err(AppError::new(
+                    ErrorCode::Timeout,
+                    format!("{} did not become healthy within {}s", info.binary, timeout.as_secs()),
+                ));
+            }
+            std::thread::sleep(HEALTH_POLL_INTERVAL);
+        }
+    }
+
+    // Kills the process and forgets it; the final state is returned
+    pub fn stop(&self, id: &str) -> Result<ServerInfo, AppError> {
+        let mut server = self.lock()?
+            .remove(id)
+            .ok_or_else(|| AppError::not_found(format!("No server with id {}", id)))?;
+        server.kill();
+        Ok(server.info.clone())
+    }
+
+    pub fn stop_all(&self) {
+        if let Ok(mut servers) = self.servers.lock() {
+            servers.clear();
+        }
+    }
+
+    pub fn list(&self) -> Result<Vec<ServerInfo>, AppError> {
+        let mut servers: Vec<ServerInfo> = self.lock()?
+            .values_mut()
+            .map(|server| server.poll().clone())
+            .collect();
+        servers.sort_by_key(|s| s.started_at);
+        Ok(servers)
+    }
+
+    // The last `limit` lines, oldest first
+    pub fn logs(&self, id: &str, limit: Option<usize>) -> Result<Vec<LogLine>, AppError> {
+        let servers = self.lock()?;
+        let server = servers.get(id)
+            .ok_or_else(|| AppError::not_found(format!("No server with id {}", id)))?;
+        let log = server.log.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock server log: {}", e)))?;
+        let skip = limit.map_or(0, |limit| log.len().saturating_sub(limit));
+        Ok(log.iter().skip(skip).cloned().collect())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<String, ManagedServer>>, AppError> {
+        self.servers.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock servers: {}", e)))
+    }
+}
+
+// Stops the server when dropped, so it is torn down even if a benchmark fails
+pub struct ServerGuard<'a> {
+    manager: &'a ServerManager,
+    pub info: ServerInfo,
+}
+
+impl<'a> ServerGuard<'a> {
+    pub fn new(manager: &'a ServerManager, info: ServerInfo) -> Self {
+        Self { manager, info }
+    }
+}
+
+impl Drop for ServerGuard<'_> {
+    fn drop(&mut self) {
+        let _ = self.manager.stop(&self.info.id);
+    }
+}
+
+// Looks a bare binary name up on PATH
+pub fn find_on_path(name: &str) -> Option<PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .flat_map(|dir| {
+            let candidate = dir.join(name);
+            let windows = cfg!(windows).then(|| dir.join(format!("{}.exe", name)));
+            std::iter::once(candidate).chain(windows)
+        })
+        .find(|path| path.is_file())
+}
+
+fn looks_like_path(arg: &str) -> bool {
+    let value = arg.split_once('=').map_or(arg, |(_, value)| value);
+    value.contains('/')
+        || value.contains('\\')
+        || value.starts_with('~')
+        || value.starts_with('.')
+        || value.as_bytes().get(1) == Some(&b':')
+}
+
+fn read_binaries(config_file: &Path) -> Result<BinariesConfig, String> {
+    if !config_file.exists() {
+        return Ok(BinariesConfig::default());
+    }
+
+    let content = std::fs::read_to_string(config_file)
+        .map_err(|e| format!("Failed to read {}: {}", config_file.display(), e))?;
+    let config: BinariesConfig = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", config_file.display(), e))?;
+
+    if let Some((name, _)) = config.binaries.iter().find(|(_, path)| !path.is_absolute()) {
+        return Err(format!("Server binary {} is not an absolute path", name));
+    }
+    Ok(config)
+}
+
+fn expand_args(args: &[String], model_path: Option<&Path>, host: &str, port: u16) -> Vec<String> {
+    let model = model_path.map(|p| p.display().to_string()).unwrap_or_default();
+    let mentions = |placeholder: &str| args.iter().any(|a| a.contains(placeholder));
+
+    let mut expanded = Vec::new();
+    if model_path.is_some() && !mentions("{model}") {
+        expanded.extend(["--model".to_string(), model.clone()]);
+    }
+    if !mentions("{port}") {
+        expanded.extend(["--host".to_string(), host.to_string(), "--port".to_string(), port.to_string()]);
+    }
+    expanded.extend(args.iter().map(|arg| {
+        arg.replace("{model}", &model)
+            .replace("{host}", host)
+            .replace("{port}", &port.to_string())
+    }));
+    expanded
+}
+
+// The port is released before the server binds it, so another process could
+// take it in between; a clash shows up as the server exiting early.
+fn free_port(host: &str) -> Result<u16, AppError> {
+    TcpListener::bind((host, 0))
+        .and_then(|listener| listener.local_addr())
+        .map(|addr| addr.port())
+        .map_err(|e| AppError::io(&format!("Failed to find a free port on {}", host), e))
+}
+
+fn capture(reader: impl Read + Send + 'static, stream: LogStream, log: ServerLog) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(reader);
+        let mut buf = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+            let Ok(mut log) = log.lock() else {
+                break;
+            };
+            if log.len() == MAX_LOG_LINES {
+                log.pop_front();
+            }
+            log.push_back(LogLine { timestamp: unix_millis(), stream, line });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::stub;
+    use crate::fs_scope::testing::fixture;
+
+    fn config(args: &[&str]) -> ServerConfig {
+        ServerConfig {
+            binary: None,
+            model_path: None,
+            args: args.iter().map(|a| a.to_string()).collect(),
+            env: HashMap::new(),
+            host: None,
+            port: None,
+            health_path: None,
+            startup_timeout_secs: None,
+        }
+    }
+
+    #[test]
+    fn binaries_come_from_config_and_not_from_allowed_roots() {
+        let f = fixture();
+        let installed = f.outside.join("llama-server");
+        let planted = f.data.join("exports").join("planted");
+        std::fs::write(&installed, "").unwrap();
+        std::fs::write(&planted, "").unwrap();
+        std::fs::write(
+            fs_scope::config_dir(&f.data).join(BINARIES_FILE),
+            serde_json::json!({"binaries": {"llama-server": installed, "planted": planted}}).to_string(),
+        )
+        .unwrap();
+
+        let binaries = ServerBinaries::load(&f.data);
+        assert!(binaries.list().config_error.is_none());
+        assert_eq!(binaries.resolve(None, &f.scope).unwrap(), installed);
+        assert_eq!(binaries.resolve(Some("llama-server"), &f.scope).unwrap(), installed);
+        let err = binaries.resolve(Some("planted"), &f.scope).err().unwrap();
+        assert_eq!(err.code, ErrorCode::PermissionDenied);
+        let err = binaries.resolve(Some(&planted.to_string_lossy()), &f.scope).err().unwrap();
+        assert_eq!(err.code, ErrorCode::NotFound);
+    }
+
+    #[test]
+    fn hosts_are_loopback_unless_allowed() {
+        let f = fixture();
+        let binaries = ServerBinaries::load(&f.data);
+        for host in [None, Some("127.0.0.1"), Some("localhost"), Some("::1"), Some("[::1]")] {
+            assert!(binaries.check_host(host).is_ok(), "{:?}", host);
+        }
+        for host in ["0.0.0.0", "::", "192.168.1.10", "example.com"] {
+            assert_eq!(binaries.check_host(Some(host)).err().unwrap().code, ErrorCode::PermissionDenied);
+        }
+        let err = scoped_config(&config(&["--host", "0.0.0.0"]), &f.scope).err().unwrap();
+        assert_eq!(err.code, ErrorCode::InvalidInput);
+        assert!(scoped_config(&config(&["--host=0.0.0.0"]), &f.scope).is_err());
+
+        std::fs::write(
+            fs_scope::config_dir(&f.data).join(BINARIES_FILE),
+            serde_json::json!({"allow_remote_host": true}).to_string(),
+        )
+        .unwrap();
+        assert!(ServerBinaries::load(&f.data).check_host(Some("0.0.0.0")).is_ok());
+    }
+
+    #[test]
+    fn environment_is_allowlisted() {
+        let f = fixture();
+        let mut request = config(&[]);
+        request.env.insert("CUDA_VISIBLE_DEVICES".to_string(), "0".to_string());
+        assert!(scoped_config(&request, &f.scope).is_ok());
+
+        request.env.insert("LD_PRELOAD".to_string(), "/tmp/evil.so".to_string());
+        assert_eq!(scoped_config(&request, &f.scope).err().unwrap().code, ErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn path_arguments_are_scoped() {
+        let f = fixture();
+        let model = f.data.join("model.gguf");
+        std::fs::write(&model, "").unwrap();
+        std::fs::write(f.outside.join("other.gguf"), "").unwrap();
+        let model = model.to_string_lossy();
+        let log = f.data.join("exports").join("server.log");
+        let log = log.to_string_lossy();
+
+        let request = config(&["--threads", "8", "-m", &model, &format!("--log-file={}", log), "--lora", "{model}"]);
+        let scoped = scoped_config(&request, &f.scope).unwrap();
+        assert_eq!(scoped.args, ["--threads", "8", "-m", &model, "--log-file", &log, "--lora", "{model}"]);
+
+        let outside = f.outside.join("other.gguf");
+        let outside = outside.to_string_lossy();
+        let denied = |args: &[&str]| scoped_config(&config(args), &f.scope).err().unwrap().code;
+        assert_eq!(denied(&["--model", &outside]), ErrorCode::PermissionDenied);
+        assert_eq!(denied(&["--model={model}/../../other.gguf"]), ErrorCode::InvalidInput);
+        assert_eq!(denied(&["--log-file", &f.outside.join("x.log").to_string_lossy()]), ErrorCode::PermissionDenied);
+        let config_file = fs_scope::config_dir(&f.data).join(BINARIES_FILE);
+        assert_eq!(denied(&["--log-file", &config_file.to_string_lossy()]), ErrorCode::PermissionDenied);
+        assert_eq!(denied(&["--some-new-flag", &outside]), ErrorCode::InvalidInput);
+        assert_eq!(denied(&["--log-file"]), ErrorCode::InvalidInput);
+    }
+
+    // Runs `script` under /bin/sh; `{port}` stops the --host/--port flags
+    // from being prepended, which sh would take as its own options.
+    #[cfg(unix)]
+    fn start(manager: &ServerManager, script: &str, port: Option<u16>, timeout_secs: u64) -> Result<ServerInfo, AppError> {
+        let mut request = config(&["-c", script, "sh", "{port}"]);
+        request.port = port;
+        request.startup_timeout_secs = Some(timeout_secs);
+        manager.start(&request, Path::new("/bin/sh"), None)
+    }
+
+    // The test answers the health checks in place of the script
+    #[cfg(unix)]
+    fn healthy_port() -> u16 {
+        let server = stub::serve((0..4).map(|_| stub::ok("text/plain", "ok")).collect());
+        server.base_url.rsplit(':').next().unwrap().parse().unwrap()
+    }
+
+    #[cfg(unix)]
+    fn is_running(pid: u32) -> bool {
+        Command::new("kill").args(["-0", &pid.to_string()]).stderr(Stdio::null()).status().unwrap().success()
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn waits_for_health_then_guard_drop_stops_the_server() {
+        let manager = ServerManager::default();
+        let info = start(&manager, "echo starting; exec sleep 30", Some(healthy_port()), 10).unwrap();
+        assert!(info.status == ServerStatus::Ready);
+        assert!(is_running(info.pid));
+        assert_eq!(manager.list().unwrap().len(), 1);
+
+        drop(ServerGuard::new(&manager, info.clone()));
+        assert!(manager.list().unwrap().is_empty());
+        assert!(!is_running(info.pid));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn early_exit_reports_the_log_tail() {
+        let manager = ServerManager::default();
+        let script = "for i in $(seq 1 30); do echo \"loading $i\"; done; echo 'failed to load model' >&2; exit 3";
+        let err = start(&manager, script, None, 10).err().unwrap();
+
+        assert_eq!(err.code, ErrorCode::BackendUnavailable);
+        assert!(err.message.contains("exited with code 3"));
+        let details = err.details.unwrap();
+        let tail: Vec<&str> = details.lines().collect();
+        assert_eq!(tail.len(), ERROR_LOG_LINES);
+        assert!(tail.contains(&"failed to load model"));
+        assert!(!tail.contains(&"loading 1"));
+        assert!(manager.list().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn startup_timeout_kills_the_server() {
+        let manager = ServerManager::default();
+        let port = free_port(DEFAULT_HOST).unwrap();
+        let err = start(&manager, "echo $$; exec sleep 30", Some(port), 1).err().unwrap();
+
+        assert_eq!(err.code, ErrorCode::Timeout);
+        let pid: u32 = err.details.unwrap().trim().parse().unwrap();
+        assert!(!is_running(pid));
+        assert!(manager.list().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn log_keeps_the_newest_lines() {
+        let manager = ServerManager::default();
+        let script = format!("seq 1 {}; exec sleep 30", MAX_LOG_LINES + 500);
+        let info = start(&manager, &script, Some(healthy_port()), 10).unwrap();
+
+        let last = (MAX_LOG_LINES + 500).to_string();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let log = loop {
+            let log = manager.logs(&info.id, None).unwrap();
+            if log.last().map(|l| l.line.as_str()) == Some(last.as_str()) || Instant::now() > deadline {
+                break log;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        };
+        assert_eq!(log.len(), MAX_LOG_LINES);
+        assert_eq!(log[0].line, "501");
+        assert_eq!(log.last().unwrap().line, last);
+
+        let tail = manager.logs(&info.id, Some(3)).unwrap();
+        let tail: Vec<&str> = tail.iter().map(|l| l.line.as_str()).collect();
+        assert_eq!(tail, ["2498", "2499", "2500"]);
+        manager.stop(&info.id).unwrap();
+    }
+}
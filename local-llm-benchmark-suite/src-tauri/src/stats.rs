@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+
+// Two-sided 95% Student's t critical values for 1..=30 degrees of freedom.
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub p90: f64,
+    pub p99: f64,
+    pub stddev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub ci95_low: f64,
+    pub ci95_high: f64,
+}
+
+pub fn summarize(values: &[f64]) -> Option<Summary> {
+    let mut sorted: Vec<f64> = values.iter().copied().filter(|v| v.is_finite()).collect();
+    if sorted.is_empty() {
+        return None;
+    }
+    sorted.sort_by(f64::total_cmp);
+
+    let n = sorted.len();
+    let mean = sorted.iter().sum::<f64>() / n as f64;
+    // Sample standard deviation; a single run has no spread to report
+    let stddev = if n > 1 {
+        (sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1) as f64).sqrt()
+    } else {
+        0.0
+    };
+    let margin = if n > 1 {
+        t_critical(n - 1) * stddev / (n as f64).sqrt()
+    } else {
+        0.0
+    };
+
+    Some(Summary {
+        count: n,
+        mean,
+        median: percentile(&sorted, 50.0),
+        p90: percentile(&sorted, 90.0),
+        p99: percentile(&sorted, 99.0),
+        stddev,
+        min: sorted[0],
+        max: sorted[n - 1],
+        ci95_low: mean - margin,
+        ci95_high: mean + margin,
+    })
+}
+
+// Linear interpolation between closest ranks; `sorted` must be ascending.
+pub fn percentile(sorted: &[f64], pct: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = (pct / 100.0).clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn t_critical(degrees_of_freedom: usize) -> f64 {
+    T_95.get(degrees_of_freedom.saturating_sub(1)).copied().unwrap_or(1.96)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn single_value_has_no_spread() {
+        let s = summarize(&[42.0]).unwrap();
+        assert_eq!(s.count, 1);
+        assert_eq!((s.mean, s.median, s.p99, s.min, s.max), (42.0, 42.0, 42.0, 42.0, 42.0));
+        assert_eq!((s.stddev, s.ci95_low, s.ci95_high), (0.0, 42.0, 42.0));
+    }
+
+    #[test]
+    fn empty_and_non_finite_values_are_ignored() {
+        assert!(summarize(&[]).is_none());
+        assert!(summarize(&[f64::NAN, f64::INFINITY]).is_none());
+        assert_eq!(summarize(&[3.0, f64::NAN, 1.0]).unwrap().count, 2);
+    }
+
+    #[test]
+    fn median_of_odd_and_even_counts() {
+        assert_eq!(summarize(&[5.0, 1.0, 3.0]).unwrap().median, 3.0);
+        assert_eq!(summarize(&[4.0, 1.0, 3.0, 2.0]).unwrap().median, 2.5);
+    }
+
+    #[test]
+    fn percentiles_interpolate_between_ranks() {
+        let sorted: Vec<f64> = (1..=10).map(f64::from).collect();
+        // rank = 0.99 * 9 = 8.91, between 9 and 10
+        assert!(close(percentile(&sorted, 99.0), 9.91));
+        assert!(close(percentile(&sorted, 90.0), 9.1));
+        assert_eq!(percentile(&sorted, 0.0), 1.0);
+        assert_eq!(percentile(&sorted, 100.0), 10.0);
+        assert_eq!(percentile(&[], 50.0), 0.0);
+    }
+
+    #[test]
+    fn confidence_interval_uses_the_t_distribution() {
+        // mean 4, sample stddev sqrt(2.5), t(4) = 2.776
+        let s = summarize(&[2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        assert!(close(s.mean, 4.0));
+        assert!(close(s.stddev, 2.5f64.sqrt()));
+        let margin = 2.776 * 2.5f64.sqrt() / 5f64.sqrt();
+        assert!(close(s.ci95_low, 4.0 - margin));
+        assert!(close(s.ci95_high, 4.0 + margin));
+
+        // Past the table the normal approximation takes over
+        assert_eq!(t_critical(30), 2.042);
+        assert_eq!(t_critical(31), 1.96);
+    }
+}
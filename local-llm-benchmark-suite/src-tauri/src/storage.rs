@@ -0,0 +1,1225 @@
+use crate::backend::BackendKind;
+use crate::benchmark::BenchmarkResult;
+use crate::error::AppError;
+use crate::metrics::HardwareSnapshot;
+use crate::models::{LibraryModel, ModelFile, ModelLocation};
+use crate::runner::CaseResult;
+use rusqlite::{ffi, named_params, params, Connection, ErrorCode, OpenFlags, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+const DATABASE_FILE: &str = "results.db";
+const LEGACY_STORAGE_FILE: &str = "storage.json";
+pub const DEFAULT_NAMESPACE: &str = "default";
+pub const DEFAULT_MAX_VALUE_BYTES: usize = 1024 * 1024;
+const MAX_VALUE_BYTES_SETTING: &str = "max_value_bytes";
+// Filter for unexpired rows; `:now` is bound to the current unix time
+const LIVE: &str = "(expires_at IS NULL OR expires_at > :now)";
+// How long a writer waits for another process holding the database lock
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Applied in order; the index of the last applied entry + 1 is kept in
+// SQLite's `user_version`. Never edit a released migration, append a new one.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE kv (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL,
+        timestamp INTEGER NOT NULL
+    );
+    CREATE TABLE models (
+        id INTEGER PRIMARY KEY,
+        backend TEXT NOT NULL,
+        name TEXT NOT NULL,
+        UNIQUE (backend, name)
+    );
+    CREATE TABLE hardware_snapshots (
+        id INTEGER PRIMARY KEY,
+        taken_at INTEGER NOT NULL,
+        host_name TEXT,
+        os_version TEXT,
+        cpu_brand TEXT NOT NULL,
+        logical_cores INTEGER NOT NULL,
+        memory_total REAL NOT NULL,
+        snapshot_json TEXT NOT NULL
+    );
+    CREATE TABLE cases (
+        id INTEGER PRIMARY KEY,
+        suite TEXT,
+        name TEXT NOT NULL,
+        model_id INTEGER NOT NULL REFERENCES models(id),
+        hardware_id INTEGER REFERENCES hardware_snapshots(id),
+        warmup_runs INTEGER NOT NULL,
+        expected_pass_rate REAL,
+        summary_json TEXT NOT NULL,
+        created_at INTEGER NOT NULL
+    );
+    CREATE TABLE runs (
+        id TEXT PRIMARY KEY,
+        case_id INTEGER REFERENCES cases(id) ON DELETE CASCADE,
+        model_id INTEGER NOT NULL REFERENCES models(id),
+        hardware_id INTEGER REFERENCES hardware_snapshots(id),
+        started_at INTEGER NOT NULL,
+        time_to_first_token_ms REAL,
+        total_latency_ms REAL NOT NULL,
+        prompt_tokens_per_second REAL,
+        generation_tokens_per_second REAL,
+        prompt_tokens INTEGER,
+        completion_tokens INTEGER,
+        result_json TEXT NOT NULL
+    );
+    CREATE INDEX runs_model_started ON runs (model_id, started_at);
+    CREATE INDEX runs_case ON runs (case_id);
+    CREATE TABLE samples (
+        id INTEGER PRIMARY KEY,
+        run_id TEXT NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+        timestamp INTEGER NOT NULL,
+        cpu_usage REAL NOT NULL,
+        memory_used REAL NOT NULL,
+        temperature REAL,
+        sample_json TEXT NOT NULL
+    );
+    CREATE INDEX samples_run ON samples (run_id, timestamp);",
+    "CREATE TABLE kv_namespaced (
+        namespace TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        PRIMARY KEY (namespace, key)
+    );
+    INSERT INTO kv_namespaced (namespace, key, value, timestamp)
+        SELECT 'default', key, value, timestamp FROM kv;
+    DROP TABLE kv;
+    ALTER TABLE kv_namespaced RENAME TO kv;",
+    "ALTER TABLE kv ADD COLUMN expires_at INTEGER;
+    CREATE INDEX kv_expires ON kv (expires_at);
+    CREATE TABLE settings (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );",
+    "CREATE TABLE model_files (
+        path TEXT PRIMARY KEY,
+        fingerprint TEXT NOT NULL,
+        source TEXT NOT NULL,
+        format TEXT NOT NULL,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        first_seen INTEGER NOT NULL,
+        last_seen INTEGER NOT NULL
+    );
+    CREATE INDEX model_files_fingerprint ON model_files (fingerprint);",
+    "CREATE TABLE file_digests (
+        path TEXT PRIMARY KEY,
+        size INTEGER NOT NULL,
+        modified INTEGER NOT NULL,
+        sha256 TEXT NOT NULL,
+        hashed_at INTEGER NOT NULL
+    );
+    ALTER TABLE runs ADD COLUMN model_digest TEXT;
+    CREATE INDEX runs_model_digest ON runs (model_digest);",
+];
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StorageData {
+    #[serde(default = "default_namespace")]
+    pub namespace: String,
+    pub key: String,
+    pub value: String,
+    pub timestamp: u64,
+    #[serde(default)]
+    pub expires_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StorageLimits {
+    pub max_value_bytes: usize,
+}
+
+#[derive(Serialize, Clone)]
+pub struct CompactionReport {
+    pub expired_removed: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredRun {
+    pub case_id: Option<i64>,
+    pub hardware_id: Option<i64>,
+    pub result: BenchmarkResult,
+}
+
+#[derive(Serialize, Clone)]
+pub struct StorageWarning {
+    pub file: String,
+    pub backup: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RecoveryReport {
+    pub backup: String,
+    pub recovered: usize,
+    pub error: Option<AppError>,
+}
+
+pub struct Storage {
+    conn: Mutex<Connection>,
+    data_dir: PathBuf,
+    warnings: Vec<StorageWarning>,
+}
+
+fn default_namespace() -> String {
+    DEFAULT_NAMESPACE.to_string()
+}
+
+pub fn app_data_dir() -> PathBuf {
+    dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("local-llm-benchmark-suite")
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl Storage {
+    pub fn open_default() -> Result<Self, AppError> {
+        Self::open_in(&app_data_dir())
+    }
+
+    pub fn open_in(data_dir: &Path) -> Result<Self, AppError> {
+        std::fs::create_dir_all(data_dir)
+            .map_err(|e| AppError::io("Failed to create data directory", e))?;
+
+        let db_path = data_dir.join(DATABASE_FILE);
+        let mut warnings = Vec::new();
+        let conn = match connect(&db_path) {
+            Err(e) if is_corruption(&e) => {
+                let backup = quarantine(&db_path)?;
+                warnings.push(warning(&db_path, &backup, e.to_string()));
+                connect(&db_path)
+            }
+            conn => conn,
+        };
+        let mut conn = conn.map_err(|e| AppError::database("Failed to open database", e))?;
+
+        migrate(&mut conn)?;
+        let mut storage = Self { conn: Mutex::new(conn), data_dir: data_dir.to_path_buf(), warnings: Vec::new() };
+        warnings.extend(storage.import_legacy(&data_dir.join(LEGACY_STORAGE_FILE))?);
+        storage.warnings = warnings;
+        Ok(storage)
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Connection>, AppError> {
+        self.conn.lock()
+            .map_err(|e| AppError::internal(format!("Failed to lock database: {}", e)))
+    }
+
+    // Files that were found corrupt at startup and moved aside
+    pub fn warnings(&self) -> &[StorageWarning] {
+        &self.warnings
+    }
+
+    // One-time move of the old storage.json key/value file into the database.
+    // A file that does not parse is quarantined instead of being dropped.
+    fn import_legacy(&self, legacy_file: &Path) -> Result<Option<StorageWarning>, AppError> {
+        if !legacy_file.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(legacy_file)
+            .map_err(|e| AppError::io("Failed to read storage", e))?;
+        let legacy: HashMap<String, StorageData> = match serde_json::from_str(&content) {
+            Ok(legacy) => legacy,
+            Err(e) => {
+                let backup = quarantine(legacy_file)?;
+                return Ok(Some(warning(legacy_file, &backup, format!("Failed to parse storage: {}", e))));
+            }
+        };
+
+        self.insert_missing(legacy.values())?;
+        std::fs::rename(legacy_file, legacy_file.with_extension("json.migrated"))
+            .map_err(|e| AppError::io("Failed to retire legacy storage", e))?;
+        Ok(None)
+    }
+
+    fn insert_missing<'a>(&self, entries: impl IntoIterator<Item = &'a StorageData>) -> Result<usize, AppError> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| AppError::database("Failed to start transaction", e))?;
+        let mut inserted = 0;
+        for entry in entries {
+            inserted += tx.execute(
+                "INSERT OR IGNORE INTO kv (namespace, key, value, timestamp, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![entry.namespace, entry.key, entry.value, entry.timestamp as i64, entry.expires_at.map(|t| t as i64)],
+            )
+            .map_err(|e| AppError::database("Failed to import storage", e))?;
+        }
+        tx.commit()
+            .map_err(|e| AppError::database("Failed to import storage", e))?;
+        Ok(inserted)
+    }
+
+    // Copies every readable entry out of quarantined files into the live
+    // store. Existing keys are never overwritten, so running it twice is safe.
+    pub fn recover(&self) -> Result<Vec<RecoveryReport>, AppError> {
+        let entries = std::fs::read_dir(&self.data_dir)
+            .map_err(|e| AppError::io("Failed to read data directory", e))?;
+
+        let mut backups: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                name.contains(".corrupt-") && !name.ends_with("-wal") && !name.ends_with("-shm")
+            })
+            .collect();
+        backups.sort();
+
+        let mut reports = Vec::new();
+        for backup in backups {
+            let salvaged = if backup.to_string_lossy().contains(LEGACY_STORAGE_FILE) {
+                std::fs::read_to_string(&backup)
+                    .map_err(|e| AppError::io("Failed to read backup", e))
+                    .map(|content| salvage_json(&content))
+            } else {
+                salvage_database(&backup)
+            };
+
+            let report = match salvaged.and_then(|entries| self.insert_missing(&entries)) {
+                Ok(recovered) => RecoveryReport { backup: backup.display().to_string(), recovered, error: None },
+                Err(e) => RecoveryReport { backup: backup.display().to_string(), recovered: 0, error: Some(e) },
+            };
+            reports.push(report);
+        }
+        Ok(reports)
+    }
+
+    pub fn put(&self, namespace: &str, key: &str, value: &str, ttl_secs: Option<u64>) -> Result<(), AppError> {
+        let mut entries = HashMap::new();
+        entries.insert(key.to_string(), value.to_string());
+        self.put_many(namespace, &entries, ttl_secs)
+    }
+
+    // All entries are written in one transaction: either every key is
+    // updated or none is.
+    pub fn put_many(
+        &self,
+        namespace: &str,
+        entries: &HashMap<String, String>,
+        ttl_secs: Option<u64>,
+    ) -> Result<(), AppError> {
+        validate_namespace(namespace)?;
+        let limits = self.limits()?;
+        if let Some((key, value)) = entries.iter().find(|(_, value)| value.len() > limits.max_value_bytes) {
+            return Err(AppError::invalid_input(format!(
+                "Value for key '{}' is {} bytes, which exceeds the {} byte limit",
+                key,
+                value.len(),
+                limits.max_value_bytes,
+            )));
+        }
+
+        let timestamp = now_secs();
+        let expires_at = ttl_secs.map(|ttl| timestamp.saturating_add(ttl) as i64);
+
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| AppError::database("Failed to start transaction", e))?;
+        for (key, value) in entries {
+            tx.execute(
+                "INSERT INTO kv (namespace, key, value, timestamp, expires_at) VALUES (?1, ?2, ?3, ?4, ?5)
+                 ON CONFLICT (namespace, key) DO UPDATE SET
+                    value = excluded.value, timestamp = excluded.timestamp, expires_at = excluded.expires_at",
+                params![namespace, key, value, timestamp as i64, expires_at],
+            )
+            .map_err(|e| AppError::database("Failed to write storage", e))?;
+        }
+        tx.commit()
+            .map_err(|e| AppError::database("Failed to write storage", e))
+    }
+
+    pub fn get(&self, namespace: &str, key: &str) -> Result<Option<StorageData>, AppError> {
+        self.lock()?
+            .query_row(
+                &format!(
+                    "SELECT namespace, key, value, timestamp, expires_at FROM kv
+                     WHERE namespace = :namespace AND key = :key AND {}",
+                    LIVE,
+                ),
+                named_params! { ":namespace": namespace, ":key": key, ":now": now_secs() as i64 },
+                storage_data,
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to read storage", e))
+    }
+
+    // Missing and expired keys are left out of the result rather than
+    // reported as errors
+    pub fn get_many(&self, namespace: &str, keys: &[String]) -> Result<Vec<StorageData>, AppError> {
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT namespace, key, value, timestamp, expires_at FROM kv
+             WHERE namespace = :namespace AND key = :key AND {}",
+            LIVE,
+        ))
+        .map_err(|e| AppError::database("Failed to read storage", e))?;
+
+        let now = now_secs() as i64;
+        let mut entries = Vec::new();
+        for key in keys {
+            if let Some(entry) = stmt
+                .query_row(named_params! { ":namespace": namespace, ":key": key, ":now": now }, storage_data)
+                .optional()
+                .map_err(|e| AppError::database("Failed to read storage", e))?
+            {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+
+    pub fn keys(&self, namespace: &str, prefix: Option<&str>) -> Result<Vec<String>, AppError> {
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT key FROM kv
+             WHERE namespace = :namespace
+                AND (:prefix IS NULL OR substr(key, 1, length(:prefix)) = :prefix)
+                AND {}
+             ORDER BY key",
+            LIVE,
+        ))
+        .map_err(|e| AppError::database("Failed to read storage", e))?;
+        let keys = stmt
+            .query_map(
+                named_params! { ":namespace": namespace, ":prefix": prefix, ":now": now_secs() as i64 },
+                |row| row.get(0),
+            )
+            .and_then(|rows| rows.collect())
+            .map_err(|e| AppError::database("Failed to read storage", e))?;
+        Ok(keys)
+    }
+
+    pub fn namespaces(&self) -> Result<Vec<String>, AppError> {
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(&format!("SELECT DISTINCT namespace FROM kv WHERE {} ORDER BY namespace", LIVE))
+            .map_err(|e| AppError::database("Failed to read storage", e))?;
+        let namespaces = stmt.query_map(named_params! { ":now": now_secs() as i64 }, |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| AppError::database("Failed to read storage", e))?;
+        Ok(namespaces)
+    }
+
+    pub fn delete(&self, namespace: &str, key: &str) -> Result<bool, AppError> {
+        let deleted = self.lock()?
+            .execute("DELETE FROM kv WHERE namespace = ?1 AND key = ?2", params![namespace, key])
+            .map_err(|e| AppError::database("Failed to delete from storage", e))?;
+        Ok(deleted > 0)
+    }
+
+    pub fn clear_namespace(&self, namespace: &str) -> Result<usize, AppError> {
+        validate_namespace(namespace)?;
+        self.lock()?
+            .execute("DELETE FROM kv WHERE namespace = ?1", params![namespace])
+            .map_err(|e| AppError::database("Failed to clear namespace", e))
+    }
+
+    pub fn limits(&self) -> Result<StorageLimits, AppError> {
+        let value: Option<String> = self.lock()?
+            .query_row("SELECT value FROM settings WHERE key = ?1", params![MAX_VALUE_BYTES_SETTING], |row| row.get(0))
+            .optional()
+            .map_err(|e| AppError::database("Failed to read storage settings", e))?;
+
+        Ok(StorageLimits {
+            max_value_bytes: value.and_then(|v| v.parse().ok()).unwrap_or(DEFAULT_MAX_VALUE_BYTES),
+        })
+    }
+
+    pub fn set_limits(&self, limits: &StorageLimits) -> Result<(), AppError> {
+        if limits.max_value_bytes == 0 {
+            return Err(AppError::invalid_input("Maximum value size must be greater than zero"));
+        }
+
+        self.lock()?
+            .execute(
+                "INSERT INTO settings (key, value) VALUES (?1, ?2)
+                 ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+                params![MAX_VALUE_BYTES_SETTING, limits.max_value_bytes.to_string()],
+            )
+            .map_err(|e| AppError::database("Failed to write storage settings", e))?;
+        Ok(())
+    }
+
+    // Deletes expired entries and rebuilds the database file to return the
+    // freed pages to the filesystem.
+    pub fn compact(&self) -> Result<CompactionReport, AppError> {
+        let conn = self.lock()?;
+        let bytes_before = database_size(&conn)?;
+
+        let expired_removed = conn
+            .execute("DELETE FROM kv WHERE expires_at <= ?1", params![now_secs() as i64])
+            .map_err(|e| AppError::database("Failed to prune expired entries", e))?;
+        conn.execute_batch("VACUUM; PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(|e| AppError::database("Failed to compact database", e))?;
+
+        Ok(CompactionReport {
+            expired_removed,
+            bytes_before,
+            bytes_after: database_size(&conn)?,
+        })
+    }
+
+    // Fingerprint of an indexed file, if it has not changed since it was hashed
+    pub fn model_file_fingerprint(&self, path: &str, size: u64, modified: u64) -> Result<Option<String>, AppError> {
+        self.lock()?
+            .query_row(
+                "SELECT fingerprint FROM model_files WHERE path = ?1 AND size = ?2 AND modified = ?3",
+                params![path, size as i64, modified as i64],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to read model index", e))
+    }
+
+    pub fn record_model_files(&self, files: &[ModelFile], seen_at: u64) -> Result<(), AppError> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| AppError::database("Failed to start transaction", e))?;
+        for file in files {
+            tx.execute(
+                "INSERT INTO model_files (path, fingerprint, source, format, size, modified, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+                 ON CONFLICT (path) DO UPDATE SET
+                    fingerprint = excluded.fingerprint, source = excluded.source, format = excluded.format,
+                    size = excluded.size, modified = excluded.modified, last_seen = excluded.last_seen",
+                params![
+                    file.path,
+                    file.fingerprint,
+                    enum_name(&file.source),
+                    enum_name(&file.format),
+                    file.size as i64,
+                    file.modified as i64,
+                    seen_at as i64,
+                ],
+            )
+            .map_err(|e| AppError::database("Failed to update model index", e))?;
+        }
+        tx.commit()
+            .map_err(|e| AppError::database("Failed to update model index", e))
+    }
+
+    // Indexed files grouped into models, most recently seen first. Digests
+    // only count while the file's size and mtime match when it was hashed.
+    pub fn model_library(&self) -> Result<Vec<LibraryModel>, AppError> {
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(
+            "SELECT m.fingerprint, d.sha256, m.path, m.source, m.format, m.size, m.modified, m.last_seen
+             FROM model_files m
+             LEFT JOIN file_digests d ON d.path = m.path AND d.size = m.size AND d.modified = m.modified
+             ORDER BY m.fingerprint, d.sha256, m.last_seen DESC, m.path",
+        )
+        .map_err(|e| AppError::database("Failed to read model index", e))?;
+
+        type Row = (String, Option<String>, String, String, String, i64, i64, i64);
+        let rows: Vec<Row> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?, row.get(7)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| AppError::database("Failed to read model index", e))?;
+
+        let mut library: Vec<LibraryModel> = Vec::new();
+        for (fingerprint, sha256, path, source, format, size, modified, last_seen) in rows {
+            let location = ModelLocation {
+                exists: Path::new(&path).exists(),
+                path,
+                source: parse_enum(&source)?,
+                modified: modified as u64,
+                last_seen: last_seen as u64,
+                sha256,
+            };
+            // Unhashed copies (sorted first) join the first group of their
+            // fingerprint; hashed copies group by digest.
+            let group = library.iter_mut().rev()
+                .take_while(|m| m.fingerprint == fingerprint)
+                .find(|m| match (&location.sha256, m.locations.iter().find_map(|l| l.sha256.as_ref())) {
+                    (Some(digest), Some(group_digest)) => digest == group_digest,
+                    _ => true,
+                });
+            match group {
+                Some(model) => {
+                    model.last_seen = model.last_seen.max(location.last_seen);
+                    model.locations.push(location);
+                }
+                None => library.push(LibraryModel {
+                    name: Path::new(&location.path)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    fingerprint,
+                    sha256: None,
+                    format: parse_enum(&format)?,
+                    size: size as u64,
+                    last_seen: location.last_seen,
+                    locations: vec![location],
+                }),
+            }
+        }
+        for model in &mut library {
+            if model.locations.iter().all(|l| l.sha256.is_some()) {
+                model.sha256 = model.locations[0].sha256.clone();
+            }
+        }
+        library.sort_by(|a, b| b.last_seen.cmp(&a.last_seen).then_with(|| a.name.cmp(&b.name)));
+        Ok(library)
+    }
+
+    // Digest of a file hashed before, if its size and mtime are unchanged
+    pub fn cached_digest(&self, path: &str, size: u64, modified: u64) -> Result<Option<String>, AppError> {
+        self.lock()?
+            .query_row(
+                "SELECT sha256 FROM file_digests WHERE path = ?1 AND size = ?2 AND modified = ?3",
+                params![path, size as i64, modified as i64],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::database("Failed to read digest cache", e))
+    }
+
+    pub fn record_digest(&self, path: &str, size: u64, modified: u64, sha256: &str) -> Result<(), AppError> {
+        self.lock()?
+            .execute(
+                "INSERT OR REPLACE INTO file_digests (path, size, modified, sha256, hashed_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![path, size as i64, modified as i64, sha256, now_secs() as i64],
+            )
+            .map_err(|e| AppError::database("Failed to update digest cache", e))?;
+        Ok(())
+    }
+
+    pub fn record_hardware(&self, snapshot: &HardwareSnapshot) -> Result<i64, AppError> {
+        let json = serde_json::to_string(snapshot)
+            .map_err(|e| AppError::internal(format!("Failed to serialize hardware snapshot: {}", e)))?;
+
+        let conn = self.lock()?;
+        conn.execute(
+            "INSERT INTO hardware_snapshots
+                (taken_at, host_name, os_version, cpu_brand, logical_cores, memory_total, snapshot_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                now_secs() as i64,
+                snapshot.host_name,
+                snapshot.os_version,
+                snapshot.cpu_brand,
+                snapshot.logical_cores as i64,
+                snapshot.metrics.memory_total,
+                json,
+            ],
+        )
+        .map_err(|e| AppError::database("Failed to record hardware snapshot", e))?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    pub fn record_run(&self, result: &BenchmarkResult, hardware_id: Option<i64>) -> Result<(), AppError> {
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| AppError::database("Failed to start transaction", e))?;
+        insert_run(&tx, result, None, hardware_id)?;
+        tx.commit()
+            .map_err(|e| AppError::database("Failed to record run", e))
+    }
+
+    pub fn record_case(
+        &self,
+        suite: Option<&str>,
+        backend: BackendKind,
+        result: &CaseResult,
+        hardware_id: Option<i64>,
+    ) -> Result<i64, AppError> {
+        let summary = serde_json::to_string(&result.summary)
+            .map_err(|e| AppError::internal(format!("Failed to serialize case summary: {}", e)))?;
+
+        let mut conn = self.lock()?;
+        let tx = conn.transaction()
+            .map_err(|e| AppError::database("Failed to start transaction", e))?;
+        let model_id = model_id(&tx, backend, &result.model)?;
+        tx.execute(
+            "INSERT INTO cases
+                (suite, name, model_id, hardware_id, warmup_runs, expected_pass_rate, summary_json, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                suite,
+                result.case,
+                model_id,
+                hardware_id,
+                result.warmup_runs,
+                result.expected_pass_rate,
+                summary,
+                now_secs() as i64,
+            ],
+        )
+        .map_err(|e| AppError::database("Failed to record case", e))?;
+        let case_id = tx.last_insert_rowid();
+
+        for run in &result.runs {
+            insert_run(&tx, run, Some(case_id), hardware_id)?;
+        }
+        tx.commit()
+            .map_err(|e| AppError::database("Failed to record case", e))?;
+        Ok(case_id)
+    }
+
+    pub fn list_runs(&self, model: Option<&str>, limit: u32) -> Result<Vec<StoredRun>, AppError> {
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare(
+            "SELECT runs.case_id, runs.hardware_id, runs.result_json FROM runs
+             JOIN models ON models.id = runs.model_id
+             WHERE ?1 IS NULL OR models.name = ?1
+             ORDER BY runs.started_at DESC
+             LIMIT ?2",
+        )
+        .map_err(|e| AppError::database("Failed to query runs", e))?;
+
+        let rows: Vec<(Option<i64>, Option<i64>, String)> = stmt
+            .query_map(params![model, limit], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| AppError::database("Failed to query runs", e))?;
+
+        rows.into_iter()
+            .map(|(case_id, hardware_id, json)| {
+                let result = serde_json::from_str(&json)
+                    .map_err(|e| AppError::corrupt(format!("Failed to parse stored run: {}", e)))?;
+                Ok(StoredRun { case_id, hardware_id, result })
+            })
+            .collect()
+    }
+
+    pub fn run_samples(&self, run_id: &str) -> Result<Vec<serde_json::Value>, AppError> {
+        let conn = self.lock()?;
+        let mut stmt = conn.prepare("SELECT sample_json FROM samples WHERE run_id = ?1 ORDER BY timestamp")
+            .map_err(|e| AppError::database("Failed to query samples", e))?;
+
+        let rows: Vec<String> = stmt.query_map(params![run_id], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| AppError::database("Failed to query samples", e))?;
+
+        rows.iter()
+            .map(|json| serde_json::from_str(json).map_err(|e| AppError::corrupt(format!("Failed to parse stored sample: {}", e))))
+            .collect()
+    }
+}
+
+fn storage_data(row: &rusqlite::Row) -> rusqlite::Result<StorageData> {
+    Ok(StorageData {
+        namespace: row.get(0)?,
+        key: row.get(1)?,
+        value: row.get(2)?,
+        timestamp: row.get::<_, i64>(3)? as u64,
+        expires_at: row.get::<_, Option<i64>>(4)?.map(|t| t as u64),
+    })
+}
+
+fn database_size(conn: &Connection) -> Result<u64, AppError> {
+    conn.query_row(
+        "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        [],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|bytes| bytes as u64)
+    .map_err(|e| AppError::database("Failed to read database size", e))
+}
+
+// Unit enums are stored as their serde name, e.g. "lm_studio"
+fn enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn parse_enum<T: serde::de::DeserializeOwned>(name: &str) -> Result<T, AppError> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|e| AppError::corrupt(format!("Unexpected stored value '{}': {}", name, e)))
+}
+
+fn validate_namespace(namespace: &str) -> Result<(), AppError> {
+    if namespace.trim().is_empty() {
+        return Err(AppError::invalid_input("Namespace must not be empty"));
+    }
+    Ok(())
+}
+
+fn connect(path: &Path) -> Result<Connection, rusqlite::Error> {
+    let conn = Connection::open(path)?;
+    // synchronous = FULL fsyncs the WAL on every commit, so an
+    // acknowledged write survives a crash or power loss.
+    conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL; PRAGMA synchronous = FULL;")?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+
+    let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if check != "ok" {
+        return Err(rusqlite::Error::SqliteFailure(ffi::Error::new(ffi::SQLITE_CORRUPT), Some(check)));
+    }
+    Ok(conn)
+}
+
+// Only damage warrants moving the database aside; a locked or unreadable
+// file must surface as an error instead.
+fn is_corruption(e: &rusqlite::Error) -> bool {
+    matches!(e.sqlite_error_code(), Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase))
+}
+
+// Renames a corrupt file to `<name>.corrupt-<unix secs>` so it is kept for
+// recovery instead of being overwritten. A second backup within the same
+// second gets a `-<n>` suffix.
+fn quarantine(path: &Path) -> Result<PathBuf, AppError> {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let stamp = format!("{}.corrupt-{}", name, now_secs());
+    let backup = (0..)
+        .map(|n| match n {
+            0 => path.with_file_name(&stamp),
+            n => path.with_file_name(format!("{}-{}", stamp, n)),
+        })
+        .find(|backup| !backup.exists())
+        .unwrap_or_default();
+    std::fs::rename(path, &backup)
+        .map_err(|e| AppError::io(&format!("Failed to quarantine {}", path.display()), e))?;
+
+    // SQLite looks for its journal next to the database under the same name
+    for suffix in ["-wal", "-shm"] {
+        let sidecar = path.with_file_name(format!("{}{}", name, suffix));
+        if sidecar.exists() {
+            let _ = std::fs::rename(&sidecar, format!("{}{}", backup.display(), suffix));
+        }
+    }
+    Ok(backup)
+}
+
+fn warning(file: &Path, backup: &Path, message: String) -> StorageWarning {
+    StorageWarning {
+        file: file.display().to_string(),
+        backup: backup.display().to_string(),
+        message,
+    }
+}
+
+// Pulls every complete entry out of a damaged storage.json, including a
+// truncated one, by trying to parse an entry at each opening brace.
+fn salvage_json(content: &str) -> Vec<StorageData> {
+    if let Ok(storage) = serde_json::from_str::<HashMap<String, StorageData>>(content) {
+        return storage.into_values().collect();
+    }
+
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = content[offset..].find('{').map(|i| offset + i) {
+        let mut stream = serde_json::Deserializer::from_str(&content[start..]).into_iter::<StorageData>();
+        match stream.next() {
+            Some(Ok(entry)) => {
+                entries.push(entry);
+                offset = start + stream.byte_offset();
+            }
+            _ => offset = start + 1,
+        }
+    }
+    entries
+}
+
+fn salvage_database(path: &Path) -> Result<Vec<StorageData>, AppError> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| AppError::database("Failed to open backup", e))?;
+    // Older databases lack the namespace and expiry columns
+    let mut stmt = conn.prepare("SELECT namespace, key, value, timestamp, expires_at FROM kv")
+        .or_else(|_| conn.prepare("SELECT namespace, key, value, timestamp, NULL FROM kv"))
+        .or_else(|_| conn.prepare("SELECT 'default', key, value, timestamp, NULL FROM kv"))
+        .map_err(|e| AppError::database("Failed to read backup", e))?;
+    let rows = stmt.query_map([], storage_data)
+        .map_err(|e| AppError::database("Failed to read backup", e))?;
+
+    // Skip rows on damaged pages rather than giving up on the whole table
+    Ok(rows.filter_map(Result::ok).collect())
+}
+
+fn migrate(conn: &mut Connection) -> Result<(), AppError> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))
+        .map_err(|e| AppError::database("Failed to read schema version", e))?;
+
+    for (index, sql) in MIGRATIONS.iter().enumerate().skip(version.max(0) as usize) {
+        let tx = conn.transaction()
+            .map_err(|e| AppError::database("Failed to start migration", e))?;
+        tx.execute_batch(sql)
+            .map_err(|e| AppError::database(&format!("Failed to apply migration {}", index + 1), e))?;
+        tx.pragma_update(None, "user_version", (index + 1) as i64)
+            .map_err(|e| AppError::database(&format!("Failed to apply migration {}", index + 1), e))?;
+        tx.commit()
+            .map_err(|e| AppError::database(&format!("Failed to apply migration {}", index + 1), e))?;
+    }
+    Ok(())
+}
+
+fn model_id(conn: &Connection, backend: BackendKind, name: &str) -> Result<i64, AppError> {
+    let backend = enum_name(&backend);
+    conn.execute(
+        "INSERT INTO models (backend, name) VALUES (?1, ?2) ON CONFLICT (backend, name) DO NOTHING",
+        params![backend, name],
+    )
+    .and_then(|_| conn.query_row(
+        "SELECT id FROM models WHERE backend = ?1 AND name = ?2",
+        params![backend, name],
+        |row| row.get(0),
+    ))
+    .map_err(|e| AppError::database("Failed to record model", e))
+}
+
+fn insert_run(
+    conn: &Connection,
+    result: &BenchmarkResult,
+    case_id: Option<i64>,
+    hardware_id: Option<i64>,
+) -> Result<(), AppError> {
+    let model_id = model_id(conn, result.backend, &result.model)?;
+    let json = serde_json::to_string(result)
+        .map_err(|e| AppError::internal(format!("Failed to serialize run: {}", e)))?;
+
+    conn.execute(
+        "INSERT INTO runs
+            (id, case_id, model_id, hardware_id, started_at, time_to_first_token_ms, total_latency_ms,
+             prompt_tokens_per_second, generation_tokens_per_second, prompt_tokens, completion_tokens, model_digest,
+             result_json)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        params![
+            result.run_id,
+            case_id,
+            model_id,
+            hardware_id,
+            result.started_at as i64,
+            result.time_to_first_token_ms,
+            result.total_latency_ms,
+            result.prompt_tokens_per_second,
+            result.generation_tokens_per_second,
+            result.prompt_tokens.map(|t| t as i64),
+            result.completion_tokens.map(|t| t as i64),
+            result.model_digest,
+            json,
+        ],
+    )
+    .map_err(|e| AppError::database("Failed to record run", e))?;
+
+    for sample in &result.samples {
+        let json = serde_json::to_string(sample)
+            .map_err(|e| AppError::internal(format!("Failed to serialize sample: {}", e)))?;
+        conn.execute(
+            "INSERT INTO samples (run_id, timestamp, cpu_usage, memory_used, temperature, sample_json)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                result.run_id,
+                sample.timestamp as i64,
+                sample.metrics.cpu_usage,
+                sample.metrics.memory_used,
+                sample.metrics.temperature,
+                json,
+            ],
+        )
+        .map_err(|e| AppError::database("Failed to record sample", e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn concurrent_writes_are_not_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        // Two handles act like two app instances sharing the database
+        let first = Storage::open_in(dir.path()).unwrap();
+        let second = Storage::open_in(dir.path()).unwrap();
+
+        std::thread::scope(|scope| {
+            for thread in 0..16 {
+                let storage = if thread % 2 == 0 { &first } else { &second };
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        storage.put(DEFAULT_NAMESPACE, &format!("t{}-k{}", thread, i), &i.to_string(), None).unwrap();
+                    }
+                    let batch: HashMap<String, String> = (0..10)
+                        .map(|i| (format!("t{}-batch{}", thread, i), "b".to_string()))
+                        .collect();
+                    storage.put_many("bulk", &batch, None).unwrap();
+                });
+            }
+        });
+
+        let reopened = Storage::open_in(dir.path()).unwrap();
+        assert_eq!(reopened.keys(DEFAULT_NAMESPACE, None).unwrap().len(), 16 * 50);
+        assert_eq!(reopened.keys("bulk", None).unwrap().len(), 16 * 10);
+        for thread in 0..16 {
+            let value = reopened.get(DEFAULT_NAMESPACE, &format!("t{}-k49", thread)).unwrap().unwrap();
+            assert_eq!(value.value, "49");
+        }
+    }
+
+    #[test]
+    fn library_splits_copies_whose_digests_differ() {
+        use crate::models::{ModelFormat, ModelSource};
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open_in(dir.path()).unwrap();
+        let file = |path: &str, fingerprint: &str| ModelFile {
+            path: dir.path().join(path).display().to_string(),
+            source: ModelSource::Custom,
+            format: ModelFormat::Gguf,
+            size: 100,
+            modified: 1,
+            fingerprint: fingerprint.to_string(),
+        };
+        let files = [file("a.gguf", "f1"), file("b.gguf", "f1"), file("c.gguf", "f1"), file("d.gguf", "f2")];
+        storage.record_model_files(&files, 10).unwrap();
+
+        // Same sampled fingerprint, nothing hashed yet: one likely model
+        let library = storage.model_library().unwrap();
+        assert_eq!(library.len(), 2);
+        assert_eq!(library.iter().find(|m| m.fingerprint == "f1").unwrap().locations.len(), 3);
+        assert!(library.iter().all(|m| m.sha256.is_none()));
+
+        storage.record_digest(&files[0].path, 100, 1, "aaa").unwrap();
+        storage.record_digest(&files[1].path, 100, 1, "aaa").unwrap();
+        storage.record_digest(&files[2].path, 100, 1, "bbb").unwrap();
+        // A digest from before the file changed doesn't count
+        storage.record_digest(&files[3].path, 100, 0, "ddd").unwrap();
+
+        let library = storage.model_library().unwrap();
+        let mut f1: Vec<(Option<&str>, usize)> = library.iter()
+            .filter(|m| m.fingerprint == "f1")
+            .map(|m| (m.sha256.as_deref(), m.locations.len()))
+            .collect();
+        f1.sort();
+        assert_eq!(f1, [(Some("aaa"), 2), (Some("bbb"), 1)]);
+        let f2 = library.iter().find(|m| m.fingerprint == "f2").unwrap();
+        assert_eq!(f2.sha256, None);
+        assert_eq!(f2.locations[0].sha256, None);
+    }
+
+    #[test]
+    fn delete_and_clear_only_touch_their_namespace() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open_in(dir.path()).unwrap();
+        storage.put("a", "k1", "1", None).unwrap();
+        storage.put("a", "k2", "2", None).unwrap();
+        storage.put("b", "k1", "3", None).unwrap();
+
+        assert!(storage.delete("a", "k1").unwrap());
+        assert!(!storage.delete("a", "k1").unwrap());
+        assert!(storage.get("a", "k1").unwrap().is_none());
+        assert_eq!(storage.get("b", "k1").unwrap().unwrap().value, "3");
+
+        assert_eq!(storage.clear_namespace("a").unwrap(), 1);
+        assert_eq!(storage.namespaces().unwrap(), ["b"]);
+        let err = storage.clear_namespace("  ").err().unwrap();
+        assert!(matches!(err.code, crate::error::ErrorCode::InvalidInput));
+        assert_eq!(storage.keys("b", None).unwrap(), ["k1"]);
+    }
+
+    #[test]
+    fn keys_filter_by_prefix() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open_in(dir.path()).unwrap();
+        for key in ["run-2", "run-1", "runner", "suite-1", "RUN-3"] {
+            storage.put(DEFAULT_NAMESPACE, key, "v", None).unwrap();
+        }
+        storage.put("other", "run-9", "v", None).unwrap();
+
+        assert_eq!(storage.keys(DEFAULT_NAMESPACE, Some("run-")).unwrap(), ["run-1", "run-2"]);
+        assert_eq!(storage.keys(DEFAULT_NAMESPACE, Some("run")).unwrap(), ["run-1", "run-2", "runner"]);
+        assert!(storage.keys(DEFAULT_NAMESPACE, Some("missing")).unwrap().is_empty());
+        assert_eq!(storage.keys(DEFAULT_NAMESPACE, Some("")).unwrap().len(), 5);
+        assert_eq!(storage.keys(DEFAULT_NAMESPACE, None).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn get_many_skips_missing_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open_in(dir.path()).unwrap();
+        let entries: HashMap<String, String> = [("a", "1"), ("b", "2"), ("c", "3")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        storage.put_many(DEFAULT_NAMESPACE, &entries, None).unwrap();
+
+        let wanted = ["c", "missing", "a"].map(String::from);
+        let found: Vec<(String, String)> = storage.get_many(DEFAULT_NAMESPACE, &wanted).unwrap()
+            .into_iter()
+            .map(|e| (e.key, e.value))
+            .collect();
+        assert_eq!(found, [("c".to_string(), "3".to_string()), ("a".to_string(), "1".to_string())]);
+        assert!(storage.get_many("other", &wanted).unwrap().is_empty());
+    }
+
+    #[test]
+    fn expired_entries_are_hidden() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open_in(dir.path()).unwrap();
+        storage.put(DEFAULT_NAMESPACE, "short", "1", Some(1)).unwrap();
+        storage.put(DEFAULT_NAMESPACE, "long", "2", Some(3600)).unwrap();
+        storage.put("temporary", "short", "3", Some(1)).unwrap();
+        assert_eq!(storage.keys(DEFAULT_NAMESPACE, None).unwrap(), ["long", "short"]);
+        assert_eq!(storage.namespaces().unwrap(), [DEFAULT_NAMESPACE, "temporary"]);
+
+        std::thread::sleep(Duration::from_millis(2100));
+        assert!(storage.get(DEFAULT_NAMESPACE, "short").unwrap().is_none());
+        assert_eq!(storage.get(DEFAULT_NAMESPACE, "long").unwrap().unwrap().value, "2");
+        assert_eq!(storage.keys(DEFAULT_NAMESPACE, None).unwrap(), ["long"]);
+        assert_eq!(storage.get_many(DEFAULT_NAMESPACE, &["short".to_string()]).unwrap().len(), 0);
+        assert_eq!(storage.namespaces().unwrap(), [DEFAULT_NAMESPACE]);
+
+        // Writing again gives the key a fresh expiry
+        storage.put(DEFAULT_NAMESPACE, "short", "4", None).unwrap();
+        assert_eq!(storage.get(DEFAULT_NAMESPACE, "short").unwrap().unwrap().value, "4");
+    }
+
+    #[test]
+    fn values_over_the_size_limit_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open_in(dir.path()).unwrap();
+        assert_eq!(storage.limits().unwrap().max_value_bytes, DEFAULT_MAX_VALUE_BYTES);
+        storage.set_limits(&StorageLimits { max_value_bytes: 8 }).unwrap();
+        assert!(storage.set_limits(&StorageLimits { max_value_bytes: 0 }).is_err());
+        assert_eq!(storage.limits().unwrap().max_value_bytes, 8);
+
+        storage.put(DEFAULT_NAMESPACE, "fits", "12345678", None).unwrap();
+        let err = storage.put(DEFAULT_NAMESPACE, "big", "123456789", None).err().unwrap();
+        assert!(matches!(err.code, crate::error::ErrorCode::InvalidInput));
+        assert!(err.message.contains("'big'"), "{}", err.message);
+
+        // One oversized value rejects the whole batch
+        let entries: HashMap<String, String> = [("ok", "1"), ("big", "123456789")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert!(storage.put_many(DEFAULT_NAMESPACE, &entries, None).is_err());
+        assert_eq!(storage.keys(DEFAULT_NAMESPACE, None).unwrap(), ["fits"]);
+
+        // The limit is persisted with the database
+        drop(storage);
+        let reopened = Storage::open_in(dir.path()).unwrap();
+        assert_eq!(reopened.limits().unwrap().max_value_bytes, 8);
+    }
+
+    #[test]
+    fn compact_removes_expired_entries_and_shrinks_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::open_in(dir.path()).unwrap();
+        let value = "x".repeat(64 * 1024);
+        let expired: HashMap<String, String> = (0..32).map(|i| (format!("old-{}", i), value.clone())).collect();
+        // A zero TTL expires at the current second, so the entries are already dead
+        storage.put_many(DEFAULT_NAMESPACE, &expired, Some(0)).unwrap();
+        storage.put(DEFAULT_NAMESPACE, "kept", "1", None).unwrap();
+        storage.put(DEFAULT_NAMESPACE, "later", "2", Some(3600)).unwrap();
+
+        let report = storage.compact().unwrap();
+        assert_eq!(report.expired_removed, 32);
+        assert!(report.bytes_after < report.bytes_before, "{} >= {}", report.bytes_after, report.bytes_before);
+        assert!(report.bytes_before >= 32 * 64 * 1024);
+        assert_eq!(storage.keys(DEFAULT_NAMESPACE, None).unwrap(), ["kept", "later"]);
+
+        let again = storage.compact().unwrap();
+        assert_eq!(again.expired_removed, 0);
+    }
+
+    // One `"key": {...}` member of a legacy storage.json map
+    fn entry_json(key: &str, value: &str) -> String {
+        let entry = StorageData {
+            namespace: DEFAULT_NAMESPACE.to_string(),
+            key: key.to_string(),
+            value: value.to_string(),
+            timestamp: 1,
+            expires_at: None,
+        };
+        format!("\"{}\": {}", key, serde_json::to_string(&entry).unwrap())
+    }
+
+    fn backups(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(dir).unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.contains(".corrupt-"))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn salvage_json_keeps_complete_entries_of_a_truncated_file() {
+        let full = format!("{{{}, {}}}", entry_json("a", "1"), entry_json("b", "2"));
+        assert_eq!(salvage_json(&full).len(), 2);
+
+        let truncated = format!("{{{}, {}, {}", entry_json("a", "1"), entry_json("b", "2"), entry_json("c", "3"));
+        let truncated = &truncated[..truncated.len() - 10];
+        let mut keys: Vec<String> = salvage_json(truncated).into_iter().map(|e| e.key).collect();
+        keys.sort();
+        assert_eq!(keys, ["a", "b"]);
+        assert!(salvage_json("not json at all").is_empty());
+    }
+
+    #[test]
+    fn corrupt_legacy_file_is_quarantined_then_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = format!("{{{}, {}, {}", entry_json("a", "1"), entry_json("b", "2"), entry_json("c", "3"));
+        std::fs::write(dir.path().join(LEGACY_STORAGE_FILE), &content[..content.len() - 10]).unwrap();
+
+        let storage = Storage::open_in(dir.path()).unwrap();
+        assert_eq!(storage.warnings().len(), 1);
+        assert!(storage.warnings()[0].backup.contains("storage.json.corrupt-"));
+        assert!(!dir.path().join(LEGACY_STORAGE_FILE).exists());
+        assert!(storage.get(DEFAULT_NAMESPACE, "a").unwrap().is_none());
+
+        storage.put(DEFAULT_NAMESPACE, "b", "newer", None).unwrap();
+        let reports = storage.recover().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].recovered, 1);
+        assert_eq!(storage.get(DEFAULT_NAMESPACE, "a").unwrap().unwrap().value, "1");
+        // Existing keys win over the backup
+        assert_eq!(storage.get(DEFAULT_NAMESPACE, "b").unwrap().unwrap().value, "newer");
+        assert_eq!(storage.recover().unwrap()[0].recovered, 0);
+    }
+
+    #[test]
+    fn corrupt_database_is_quarantined_without_overwriting_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = dir.path().join(DATABASE_FILE);
+
+        // Two corrupt databases within the same second
+        for _ in 0..2 {
+            std::fs::write(&db, vec![b'x'; 4096]).unwrap();
+            let storage = Storage::open_in(dir.path()).unwrap();
+            assert_eq!(storage.warnings().len(), 1);
+            storage.put(DEFAULT_NAMESPACE, "fresh", "1", None).unwrap();
+        }
+        let names = backups(dir.path());
+        assert_eq!(names.len(), 2, "{:?}", names);
+        assert!(names.iter().all(|name| name.starts_with("results.db.corrupt-")));
+    }
+
+    #[test]
+    fn recover_salvages_a_quarantined_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = tempfile::tempdir().unwrap();
+        {
+            let storage = Storage::open_in(old.path()).unwrap();
+            storage.put(DEFAULT_NAMESPACE, "kept", "1", None).unwrap();
+            storage.put("other", "also", "2", None).unwrap();
+        }
+        std::fs::copy(old.path().join(DATABASE_FILE), dir.path().join("results.db.corrupt-1")).unwrap();
+
+        let storage = Storage::open_in(dir.path()).unwrap();
+        let reports = storage.recover().unwrap();
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].error.is_none());
+        assert_eq!(reports[0].recovered, 2);
+        assert_eq!(storage.get(DEFAULT_NAMESPACE, "kept").unwrap().unwrap().value, "1");
+        assert_eq!(storage.get("other", "also").unwrap().unwrap().value, "2");
+    }
+}
@@ -0,0 +1,330 @@
+use crate::backend::SamplingOptions;
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+use toml::Spanned;
+
+pub const SUITE_VERSION: u32 = 1;
+
+// Example:
+//
+//   version = 1
+//   name = "reasoning-smoke"
+//
+//   [defaults]
+//   system = "Answer concisely."
+//   repetitions = 3
+//   sampling = { temperature = 0.0, max_tokens = 256 }
+//
+//   [[prompts]]
+//   name = "capital"
+//   prompt = "What is the capital of France?"
+//   expected_contains = ["Paris"]
+//   tags = ["facts"]
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct BenchmarkSuite {
+    pub version: u32,
+    pub name: String,
+    pub description: Option<String>,
+    pub prompts: Vec<PromptCase>,
+}
+
+// A prompt with the suite defaults already applied.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PromptCase {
+    pub name: String,
+    pub prompt: String,
+    pub system: Option<String>,
+    pub expected: Option<String>,
+    pub expected_contains: Vec<String>,
+    pub tags: Vec<String>,
+    pub repetitions: u32,
+    pub sampling: SamplingOptions,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SuiteIssue {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct SuiteValidation {
+    pub suite: Option<BenchmarkSuite>,
+    pub issues: Vec<SuiteIssue>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SuiteSpec {
+    version: Option<Spanned<u32>>,
+    name: Spanned<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    defaults: Option<DefaultsSpec>,
+    #[serde(default)]
+    prompts: Vec<Spanned<PromptSpec>>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct DefaultsSpec {
+    #[serde(default)]
+    system: Option<String>,
+    #[serde(default)]
+    repetitions: Option<Spanned<u32>>,
+    #[serde(default)]
+    sampling: Option<SamplingSpec>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PromptSpec {
+    name: Spanned<String>,
+    prompt: Spanned<String>,
+    #[serde(default)]
+    system: Option<String>,
+    #[serde(default)]
+    expected: Option<String>,
+    #[serde(default)]
+    expected_contains: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    repetitions: Option<Spanned<u32>>,
+    #[serde(default)]
+    sampling: Option<SamplingSpec>,
+}
+
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+struct SamplingSpec {
+    #[serde(default)]
+    temperature: Option<Spanned<f32>>,
+    #[serde(default)]
+    top_p: Option<Spanned<f32>>,
+    #[serde(default)]
+    top_k: Option<u32>,
+    #[serde(default)]
+    max_tokens: Option<Spanned<u32>>,
+    #[serde(default)]
+    seed: Option<i64>,
+    #[serde(default)]
+    stop: Option<Vec<String>>,
+}
+
+struct Validator<'a> {
+    source: &'a str,
+    issues: Vec<SuiteIssue>,
+}
+
+impl Validator<'_> {
+    fn error(&mut self, span: Range<usize>, message: impl Into<String>) {
+        let (line, column) = line_col(self.source, span.start);
+        self.issues.push(SuiteIssue { line, column, message: message.into() });
+    }
+
+    fn check_sampling(&mut self, sampling: &SamplingSpec, context: &str) {
+        if let Some(t) = &sampling.temperature {
+            if !(0.0..=2.0).contains(t.get_ref()) {
+                self.error(t.span(), format!("{}: temperature must be between 0.0 and 2.0", context));
+            }
+        }
+        if let Some(p) = &sampling.top_p {
+            if !(*p.get_ref() > 0.0 && *p.get_ref() <= 1.0) {
+                self.error(p.span(), format!("{}: top_p must be in (0.0, 1.0]", context));
+            }
+        }
+        if let Some(m) = &sampling.max_tokens {
+            if *m.get_ref() == 0 {
+                self.error(m.span(), format!("{}: max_tokens must be greater than zero", context));
+            }
+        }
+    }
+
+    fn check_repetitions(&mut self, repetitions: &Option<Spanned<u32>>, context: &str) {
+        if let Some(r) = repetitions {
+            if *r.get_ref() == 0 {
+                self.error(r.span(), format!("{}: repetitions must be at least 1", context));
+            }
+        }
+    }
+}
+
+pub fn validate(source: &str) -> SuiteValidation {
+    let mut validator = Validator { source, issues: Vec::new() };
+
+    let spec: SuiteSpec = match toml::from_str(source) {
+        Ok(spec) => spec,
+        Err(e) => {
+            validator.error(e.span().unwrap_or(0..0), e.message().trim().to_string());
+            return SuiteValidation { suite: None, issues: validator.issues };
+        }
+    };
+
+    match &spec.version {
+        None => validator.error(0..0, format!("missing `version`, expected version = {}", SUITE_VERSION)),
+        Some(v) if *v.get_ref() != SUITE_VERSION => validator.error(
+            v.span(),
+            format!("unsupported suite version {}, expected {}", v.get_ref(), SUITE_VERSION),
+        ),
+        _ => {}
+    }
+    if spec.name.get_ref().trim().is_empty() {
+        validator.error(spec.name.span(), "suite name must not be empty");
+    }
+
+    let defaults = spec.defaults.unwrap_or_default();
+    validator.check_repetitions(&defaults.repetitions, "defaults");
+    if let Some(sampling) = &defaults.sampling {
+        validator.check_sampling(sampling, "defaults");
+    }
+
+    if spec.prompts.is_empty() {
+        validator.error(0..0, "suite must define at least one [[prompts]] entry");
+    }
+
+    let mut seen = HashSet::new();
+    let mut prompts = Vec::new();
+    for entry in &spec.prompts {
+        let prompt = entry.get_ref();
+        let name = prompt.name.get_ref().trim();
+        let context = format!("prompt '{}'", name);
+
+        if name.is_empty() {
+            validator.error(prompt.name.span(), "prompt name must not be empty");
+        } else if !seen.insert(name.to_string()) {
+            validator.error(prompt.name.span(), format!("duplicate prompt name '{}'", name));
+        }
+        if prompt.prompt.get_ref().trim().is_empty() {
+            validator.error(prompt.prompt.span(), format!("{}: prompt text must not be empty", context));
+        }
+        validator.check_repetitions(&prompt.repetitions, &context);
+        if let Some(sampling) = &prompt.sampling {
+            validator.check_sampling(sampling, &context);
+        }
+
+        prompts.push(PromptCase {
+            name: name.to_string(),
+            prompt: prompt.prompt.get_ref().clone(),
+            system: prompt.system.clone().or_else(|| defaults.system.clone()),
+            expected: prompt.expected.clone(),
+            expected_contains: prompt.expected_contains.clone(),
+            tags: prompt.tags.clone(),
+            repetitions: prompt.repetitions.as_ref()
+                .or(defaults.repetitions.as_ref())
+                .map_or(1, |r| *r.get_ref()),
+            sampling: merge_sampling(defaults.sampling.as_ref(), prompt.sampling.as_ref()),
+        });
+    }
+
+    let suite = validator.issues.is_empty().then(|| BenchmarkSuite {
+        version: SUITE_VERSION,
+        name: spec.name.into_inner(),
+        description: spec.description,
+        prompts,
+    });
+    SuiteValidation { suite, issues: validator.issues }
+}
+
+pub fn load(path: &Path) -> Result<BenchmarkSuite, AppError> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| AppError::io("Failed to read suite", e))?;
+    let path = path.display();
+
+    let validation = validate(&source);
+    validation.suite.ok_or_else(|| {
+        let issues = validation.issues.iter()
+            .map(|i| format!("{}:{}:{}: {}", path, i.line, i.column, i.message))
+            .collect::<Vec<_>>();
+        AppError::invalid_input(format!("Suite {} has {} issue(s)", path, issues.len()))
+            .with_details(issues.join("\n"))
+    })
+}
+
+fn merge_sampling(defaults: Option<&SamplingSpec>, overrides: Option<&SamplingSpec>) -> SamplingOptions {
+    let base = defaults.cloned().unwrap_or_default();
+    let over = overrides.cloned().unwrap_or_default();
+
+    SamplingOptions {
+        temperature: over.temperature.or(base.temperature).map(Spanned::into_inner),
+        top_p: over.top_p.or(base.top_p).map(Spanned::into_inner),
+        top_k: over.top_k.or(base.top_k),
+        max_tokens: over.max_tokens.or(base.max_tokens).map(Spanned::into_inner),
+        seed: over.seed.or(base.seed),
+        stop: over.stop.or(base.stop),
+    }
+}
+
+// 1-based line and column of a byte offset, counting columns in characters
+// as editors do.
+fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |nl| nl + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn issues(source: &str) -> Vec<(usize, usize, String)> {
+        let validation = validate(source);
+        assert!(validation.suite.is_none());
+        validation.issues.into_iter().map(|i| (i.line, i.column, i.message)).collect()
+    }
+
+    #[test]
+    fn valid_suite_applies_defaults() {
+        let source = "version = 1\nname = \"smoke\"\n[defaults]\nrepetitions = 3\nsampling = { temperature = 0.2 }\n\n[[prompts]]\nname = \"hi\"\nprompt = \"Say hi\"\nsampling = { max_tokens = 16 }\n";
+        let validation = validate(source);
+        assert!(validation.issues.is_empty());
+        let suite = validation.suite.unwrap();
+        assert_eq!(suite.prompts[0].repetitions, 3);
+        assert_eq!(suite.prompts[0].sampling.temperature, Some(0.2));
+        assert_eq!(suite.prompts[0].sampling.max_tokens, Some(16));
+    }
+
+    #[test]
+    fn bad_version_points_at_the_value() {
+        let found = issues("name = \"smoke\"\nversion = 2\n[[prompts]]\nname = \"a\"\nprompt = \"b\"\n");
+        assert_eq!(found, [(2, 11, "unsupported suite version 2, expected 1".to_string())]);
+    }
+
+    #[test]
+    fn duplicate_prompt_name_points_at_the_second_one() {
+        let source = "version = 1\nname = \"smoke\"\n[[prompts]]\nname = \"a\"\nprompt = \"x\"\n[[prompts]]\n  name = \"a\"\nprompt = \"y\"\n";
+        assert_eq!(issues(source), [(7, 10, "duplicate prompt name 'a'".to_string())]);
+    }
+
+    #[test]
+    fn out_of_range_temperature_column_counts_characters() {
+        // "—" is three bytes but one column
+        let source = "version = 1\nname = \"smoke\"\n[[prompts]]\nname = \"a\"\nprompt = \"x\"\nsampling = { stop = [\"—\"], temperature = 3.5 }\n";
+        assert_eq!(
+            issues(source),
+            [(6, 42, "prompt 'a': temperature must be between 0.0 and 2.0".to_string())],
+        );
+    }
+
+    #[test]
+    fn toml_syntax_error_has_a_position() {
+        let found = issues("version = 1\nname = \"smoke\"\n[[prompts]]\nname = \"a\"\nprompt = \n");
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].0, found[0].1), (5, 10));
+    }
+
+    #[test]
+    fn line_col_is_one_based() {
+        assert_eq!(line_col("ab\ncd", 0), (1, 1));
+        assert_eq!(line_col("ab\ncd", 3), (2, 1));
+        assert_eq!(line_col("ab\nçd", 5), (2, 2));
+        assert_eq!(line_col("ab", 99), (1, 3));
+    }
+}